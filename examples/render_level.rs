@@ -0,0 +1,34 @@
+//! Render a frame of a built-in level without opening a window, using the engine library.
+//!
+//! `cargo run --example render_level -- e1m2 out.png 60` runs 60 ticks of the player walking
+//! forward on E1M2 and saves what they see.
+
+use wolfenstein_rs::{assets::Assets, levels::LEVELS, Controls, World, HEIGHT, WIDTH};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let name = args.first().map_or("e1m1", String::as_str);
+    let path = args.get(1).map_or("level.png", String::as_str);
+    let ticks: u32 = args
+        .get(2)
+        .and_then(|ticks| ticks.parse().ok())
+        .unwrap_or(0);
+
+    let Some(index) = LEVELS.iter().position(|level| level.name() == name) else {
+        eprintln!("no level {name}");
+        std::process::exit(1);
+    };
+    let mut world = World::new(Assets::load(None));
+    world.load_level(index);
+    let controls = Controls {
+        forward: true,
+        ..Default::default()
+    };
+    for _ in 0..ticks {
+        world.update(&controls);
+    }
+
+    let mut frame = vec![0; (WIDTH * HEIGHT * 4) as usize];
+    world.draw(&mut frame);
+    image::save_buffer(path, &frame, WIDTH, HEIGHT, image::ColorType::Rgba8).unwrap();
+}
@@ -0,0 +1,227 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::{audio::Sound, difficulty::Difficulty, doors::DoorState, geo::Point2, map::*, World};
+
+pub const GUARD_HEALTH: i32 = 25;
+/// Points scored for killing a guard.
+pub const GUARD_POINTS: u32 = 100;
+const GUARD_SPEED: f32 = 0.8;
+/// Guards stop walking towards the player once this close.
+const GUARD_MIN_DISTANCE: f32 = 24.0;
+/// Guards don't bother shooting from further away than this.
+const GUARD_SHOOT_DISTANCE: f32 = 240.0;
+/// Gunshots wake up guards within this distance.
+const HEARING_DISTANCE: f32 = 200.0;
+const AIM_TICKS: u32 = 20;
+const SHOOT_TICKS: u32 = 12;
+pub(crate) const PAIN_TICKS: u32 = 12;
+/// Ticks each walking frame is shown for.
+const WALK_FRAME_TICKS: u32 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ActorState {
+    /// Waiting at its post until it sees or hears the player.
+    Stand,
+    Chase,
+    /// Raising the gun, with the number of ticks left before the shot.
+    Aim(u32),
+    /// Just fired, with the number of ticks left before chasing again.
+    Shoot(u32),
+    Pain(u32),
+    Dead,
+}
+
+/// An enemy, drawn as a billboard like objects.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Actor {
+    pub x: f32,
+    pub y: f32,
+    pub state: ActorState,
+    pub health: i32,
+    /// Ticks left before the actor may decide to shoot again.
+    pub cooldown: u32,
+    /// Ticks spent walking, drives the walking animation.
+    pub steps: u32,
+}
+
+impl Actor {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self {
+            x,
+            y,
+            state: ActorState::Stand,
+            health: GUARD_HEALTH,
+            cooldown: 0,
+            steps: 0,
+        }
+    }
+
+    pub fn position(&self) -> Point2 {
+        Point2::new(self.x, self.y)
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.state != ActorState::Dead
+    }
+
+    /// Frame in the sprites sheet.
+    pub fn sprite(&self) -> usize {
+        match self.state {
+            ActorState::Stand => 10,
+            ActorState::Chase => 11 + ((self.steps / WALK_FRAME_TICKS) % 2) as usize,
+            ActorState::Aim(_) => 13,
+            ActorState::Shoot(_) => 14,
+            ActorState::Pain(_) => 15,
+            ActorState::Dead => 16,
+        }
+    }
+}
+
+/// Create the guards placed for `difficulty` from the object plane, centred in their cells.
+pub fn spawn_actors(map: &Map, difficulty: Difficulty) -> Vec<Actor> {
+    let mut actors = vec![];
+    for y in 0..map.height {
+        for x in 0..map.width {
+            if guard_tier(map.object(x, y)).is_some_and(|tier| tier <= difficulty.guard_tier()) {
+                actors.push(Actor::new(
+                    (x as f32 + 0.5) * TILE_WIDTH,
+                    (y as f32 + 0.5) * TILE_HEIGHT,
+                ));
+            }
+        }
+    }
+    actors
+}
+
+impl World {
+    /// Whether nothing solid stands on the straight line between two points. See-through
+    /// walls let sight and shots through.
+    pub fn line_of_sight(&self, from: Point2, to: Point2) -> bool {
+        let delta = to - from;
+        let steps = (delta.length() / 2.0).ceil() as usize;
+        (1..steps).all(|i| {
+            let point = from + delta * (i as f32 / steps as f32);
+            match self.will_hit_obstacle(point.x, point.y) {
+                Some(tile) => is_masked_wall(tile),
+                None => true,
+            }
+        })
+    }
+
+    /// Wake up every standing guard close enough to hear a gunshot.
+    pub fn alert_actors(&mut self) {
+        let player = Vec2::new(self.x, self.y);
+        let listener = self.listener();
+        for actor in &mut self.actors {
+            if actor.state == ActorState::Stand
+                && (actor.position() - player).length() < HEARING_DISTANCE
+            {
+                actor.state = ActorState::Chase;
+                self.audio
+                    .play(Sound::GuardAlert, listener.gains(actor.position()));
+            }
+        }
+    }
+
+    pub fn update_actors(&mut self) {
+        if self.cheats.freeze_ai {
+            return;
+        }
+        let player = Vec2::new(self.x, self.y);
+        for i in 0..self.actors.len() {
+            if !self.actors[i].is_alive() {
+                continue;
+            }
+            let position = self.actors[i].position();
+            let sees = self.line_of_sight(position, player);
+            let distance = (player - position).length();
+            let actor = &mut self.actors[i];
+            actor.cooldown = actor.cooldown.saturating_sub(1);
+            match actor.state {
+                ActorState::Stand => {
+                    if sees {
+                        actor.state = ActorState::Chase;
+                        // Reaction time before the first shot
+                        actor.cooldown = self.difficulty.reaction_ticks(10 + self.rng.rnd() / 4);
+                        self.play_sound(Sound::GuardAlert, Some(position));
+                    }
+                }
+                ActorState::Chase => {
+                    if sees && actor.cooldown == 0 && distance < GUARD_SHOOT_DISTANCE {
+                        actor.state = ActorState::Aim(self.difficulty.reaction_ticks(AIM_TICKS));
+                    } else if distance > GUARD_MIN_DISTANCE {
+                        actor.steps += 1;
+                        self.move_actor(i, player);
+                    }
+                }
+                ActorState::Aim(0) => {
+                    actor.state = ActorState::Shoot(SHOOT_TICKS);
+                    actor.cooldown = 30 + self.rng.rnd() / 2;
+                    self.play_sound(Sound::GuardShoot, Some(position));
+                    if sees {
+                        self.actor_shoot(position, distance);
+                    }
+                }
+                ActorState::Aim(ticks) => actor.state = ActorState::Aim(ticks - 1),
+                ActorState::Shoot(0) | ActorState::Pain(0) => actor.state = ActorState::Chase,
+                ActorState::Shoot(ticks) => actor.state = ActorState::Shoot(ticks - 1),
+                ActorState::Pain(ticks) => actor.state = ActorState::Pain(ticks - 1),
+                ActorState::Dead => {}
+            }
+        }
+    }
+
+    /// Roll whether a shot from `position` hits the player, as in the original `T_Shoot`.
+    fn actor_shoot(&mut self, position: Point2, distance: f32) {
+        let tiles = (distance / TILE_WIDTH) as u32;
+        let hit_chance = 160u32.saturating_sub(tiles * 16);
+        if self.rng.rnd() >= hit_chance {
+            return;
+        }
+        let damage = match tiles {
+            0 | 1 => self.rng.rnd() / 4,
+            2 | 3 => self.rng.rnd() / 8,
+            _ => self.rng.rnd() / 16,
+        };
+        self.take_damage(damage, Some(position));
+    }
+
+    /// Step actor `index` towards `target`, sliding along walls and opening doors on the way.
+    fn move_actor(&mut self, index: usize, target: Point2) {
+        let position = self.actors[index].position();
+        let step = (target - position).normalize_or_zero() * GUARD_SPEED;
+        let candidates = [
+            step,
+            Vec2::new(step.x.signum() * GUARD_SPEED, 0.0),
+            Vec2::new(0.0, step.y.signum() * GUARD_SPEED),
+        ];
+        for candidate in candidates {
+            let next = position + candidate;
+            match self.will_hit_obstacle(next.x, next.y) {
+                None => {
+                    let actor = &mut self.actors[index];
+                    actor.x = next.x;
+                    actor.y = next.y;
+                    return;
+                }
+                Some(tile) if is_door(tile) => {
+                    let Some((map_x, map_y)) = self.map.cell_at(next.x, next.y) else {
+                        return;
+                    };
+                    if let Some(door) = self
+                        .doors
+                        .iter_mut()
+                        .find(|door| door.x == map_x && door.y == map_y)
+                    {
+                        if matches!(door.state, DoorState::Closed | DoorState::Closing) {
+                            door.state = DoorState::Opening;
+                        }
+                    }
+                    return;
+                }
+                Some(_) => {}
+            }
+        }
+    }
+}
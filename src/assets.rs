@@ -0,0 +1,364 @@
+use std::borrow::Cow;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use image::ImageFormat;
+use log::warn;
+
+/// Width and height of wall, door and elevator textures, which the raycaster relies on.
+pub const TEXTURE_SIZE: u32 = 64;
+
+/// A grid of equally sized RGBA frames cut out of a single image.
+pub struct SpriteSheet {
+    pub width: usize,
+    pub height: usize,
+    pub frame_width: usize,
+    pub frame_height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl SpriteSheet {
+    pub fn from_png(bytes: &[u8], frame_width: usize, frame_height: usize) -> Self {
+        let mut r = std::io::Cursor::new(bytes);
+        let img = image::load(&mut r, ImageFormat::Png).unwrap().to_rgba8();
+        Self {
+            width: img.width() as usize,
+            height: img.height() as usize,
+            frame_width,
+            frame_height,
+            pixels: img.into_raw(),
+        }
+    }
+
+    pub fn columns(&self) -> usize {
+        self.width / self.frame_width
+    }
+
+    pub fn len(&self) -> usize {
+        self.columns() * (self.height / self.frame_height)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// RGBA value of a pixel relative to the top left corner of a frame.
+    pub fn pixel(&self, index: usize, x: usize, y: usize) -> [u8; 4] {
+        let sheet_x = (index % self.columns()) * self.frame_width + x;
+        let sheet_y = (index / self.columns()) * self.frame_height + y;
+        let i = (sheet_x + sheet_y * self.width) * 4;
+        [
+            self.pixels[i],
+            self.pixels[i + 1],
+            self.pixels[i + 2],
+            self.pixels[i + 3],
+        ]
+    }
+
+    /// Copy a whole frame out of the sheet, row by row.
+    pub fn frame(&self, index: usize) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(self.frame_width * self.frame_height * 4);
+        for y in 0..self.frame_height {
+            for x in 0..self.frame_width {
+                frame.extend_from_slice(&self.pixel(index, x, y));
+            }
+        }
+        frame
+    }
+}
+
+/// Glyph sheet laid out in ASCII order, starting at `first_char`.
+pub struct BitmapFont {
+    pub sheet: SpriteSheet,
+    pub first_char: char,
+    /// Advance of each glyph when drawn proportionally, spacing included.
+    pub widths: Vec<usize>,
+}
+
+impl BitmapFont {
+    pub fn new(sheet: SpriteSheet, first_char: char) -> Self {
+        let widths = (0..sheet.len())
+            .map(|index| {
+                let used = (0..sheet.frame_width)
+                    .rev()
+                    .find(|&x| (0..sheet.frame_height).any(|y| sheet.pixel(index, x, y)[3] > 0));
+                match used {
+                    Some(x) => x + 2,
+                    // Blank glyphs (space) get half a cell
+                    None => sheet.frame_width / 2,
+                }
+            })
+            .collect();
+        Self {
+            sheet,
+            first_char,
+            widths,
+        }
+    }
+
+    pub fn glyph_index(&self, c: char) -> Option<usize> {
+        let index = (c as usize).checked_sub(self.first_char as usize)?;
+        if index < self.sheet.len() {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the glyph for `c` covers the given pixel.
+    pub fn is_set(&self, c: char, x: usize, y: usize) -> bool {
+        match self.glyph_index(c) {
+            Some(index) => self.sheet.pixel(index, x, y)[3] > 0,
+            None => false,
+        }
+    }
+
+    pub fn line_height(&self) -> usize {
+        self.sheet.frame_height + 1
+    }
+
+    /// Horizontal distance to the next glyph, in font pixels.
+    pub fn advance(&self, c: char, monospace: bool) -> usize {
+        if monospace {
+            return self.sheet.frame_width;
+        }
+        match self.glyph_index(c) {
+            Some(index) => self.widths[index],
+            None => self.sheet.frame_width / 2,
+        }
+    }
+
+    /// Width of a single line, in font pixels.
+    pub fn measure(&self, line: &str, monospace: bool) -> usize {
+        line.chars().map(|c| self.advance(c, monospace)).sum()
+    }
+
+    /// Split `text` into lines on `\n` and, if `max_width` is set, between words.
+    pub fn wrap(&self, text: &str, max_width: Option<usize>, monospace: bool) -> Vec<String> {
+        let mut lines = vec![];
+        for paragraph in text.split('\n') {
+            let Some(max_width) = max_width else {
+                lines.push(paragraph.to_string());
+                continue;
+            };
+            let mut line = String::new();
+            for word in paragraph.split(' ') {
+                let candidate = if line.is_empty() {
+                    word.to_string()
+                } else {
+                    format!("{line} {word}")
+                };
+                if !line.is_empty() && self.measure(&candidate, monospace) > max_width {
+                    lines.push(std::mem::replace(&mut line, word.to_string()));
+                } else {
+                    line = candidate;
+                }
+            }
+            lines.push(line);
+        }
+        lines
+    }
+}
+
+pub struct Assets {
+    pub textures: Vec<Vec<Vec<u8>>>,
+    /// Textures of the see-through walls, with alpha.
+    pub masked: Vec<Vec<Vec<u8>>>,
+    pub font: BitmapFont,
+    pub faces: SpriteSheet,
+    pub doors: Vec<Vec<Vec<u8>>>,
+    /// Elevator switch, up then down.
+    pub elevator: Vec<Vec<Vec<u8>>>,
+    pub sprites: SpriteSheet,
+    pub weapons: SpriteSheet,
+}
+
+impl Assets {
+    /// Load the built-in assets, replaced by any with the same file name in `dir`.
+    pub fn load(dir: Option<&Path>) -> Self {
+        // Replacements have to be PNGs cut into frames of the same size as the built-in
+        // image, in as many rows and at least as many columns, for the renderer to index
+        let file = |name: &str,
+                    built_in: &'static [u8],
+                    frame_width: u32,
+                    frame_height: u32|
+         -> Cow<'static, [u8]> {
+            let Some(dir) = dir else {
+                return Cow::Borrowed(built_in);
+            };
+            let path = dir.join(name);
+            let bytes = match fs::read(&path) {
+                Ok(bytes) => bytes,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    return Cow::Borrowed(built_in)
+                }
+                Err(err) => {
+                    warn!(
+                        "using the built-in {name}, can't read {}: {err}",
+                        path.display()
+                    );
+                    return Cow::Borrowed(built_in);
+                }
+            };
+            let Some((width, height)) = png_size(&bytes) else {
+                warn!(
+                    "using the built-in {name}, {} isn't a PNG image",
+                    path.display()
+                );
+                return Cow::Borrowed(built_in);
+            };
+            let (built_in_width, built_in_height) =
+                png_size(built_in).expect("built-in assets are PNGs");
+            let (columns, rows) = (built_in_width / frame_width, built_in_height / frame_height);
+            if width % frame_width != 0
+                || height % frame_height != 0
+                || width / frame_width < columns
+                || height / frame_height != rows
+            {
+                warn!(
+                    "using the built-in {name}, {} is {width}x{height} but needs {rows} rows \
+                     of at least {columns} {frame_width}x{frame_height} frames",
+                    path.display()
+                );
+                return Cow::Borrowed(built_in);
+            }
+            Cow::Owned(bytes)
+        };
+        Self {
+            textures: load_textures(&file(
+                "textures.png",
+                include_bytes!("../assets/textures.png"),
+                TEXTURE_SIZE,
+                TEXTURE_SIZE,
+            )),
+            masked: load_textures(&file(
+                "masked.png",
+                include_bytes!("../assets/masked.png"),
+                TEXTURE_SIZE,
+                TEXTURE_SIZE,
+            )),
+            font: BitmapFont::new(
+                SpriteSheet::from_png(
+                    &file("font.png", include_bytes!("../assets/font.png"), 8, 8),
+                    8,
+                    8,
+                ),
+                ' ',
+            ),
+            faces: SpriteSheet::from_png(
+                &file("faces.png", include_bytes!("../assets/faces.png"), 24, 32),
+                24,
+                32,
+            ),
+            doors: load_textures(&file(
+                "doors.png",
+                include_bytes!("../assets/doors.png"),
+                TEXTURE_SIZE,
+                TEXTURE_SIZE,
+            )),
+            elevator: load_textures(&file(
+                "elevator.png",
+                include_bytes!("../assets/elevator.png"),
+                TEXTURE_SIZE,
+                TEXTURE_SIZE,
+            )),
+            sprites: SpriteSheet::from_png(
+                &file(
+                    "sprites.png",
+                    include_bytes!("../assets/sprites.png"),
+                    64,
+                    64,
+                ),
+                64,
+                64,
+            ),
+            weapons: SpriteSheet::from_png(
+                &file(
+                    "weapons.png",
+                    include_bytes!("../assets/weapons.png"),
+                    64,
+                    64,
+                ),
+                64,
+                64,
+            ),
+        }
+    }
+}
+
+/// Width and height of a PNG image, `None` for anything else.
+fn png_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    let reader = image::io::Reader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .ok()?;
+    if reader.format() != Some(ImageFormat::Png) {
+        return None;
+    }
+    reader.into_dimensions().ok()
+}
+
+/// Split a strip of 64x64 wall textures into vertical 1px columns.
+fn load_textures(bytes: &[u8]) -> Vec<Vec<Vec<u8>>> {
+    let mut textures: Vec<Vec<Vec<u8>>> = vec![];
+    let mut r = std::io::Cursor::new(bytes);
+    let img = image::load(&mut r, ImageFormat::Png).unwrap();
+    let count = img.width() / img.height();
+    for i in 0..count {
+        let img = img.crop_imm(
+            i * (img.width() / count),
+            0,
+            img.width() / count,
+            img.height(),
+        );
+        let mut vertical_chunks = vec![];
+        for i in 0..img.width() {
+            let chunk = img.crop_imm(i, 0, 1, img.height()).to_rgba8().to_vec();
+            vertical_chunks.push(chunk);
+        }
+        textures.push(vertical_chunks);
+    }
+    textures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrongly_sized_replacements_fall_back_to_the_built_in_images() {
+        let dir = std::env::temp_dir().join(format!("assets-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        image::RgbaImage::new(256, 32)
+            .save(dir.join("textures.png"))
+            .unwrap();
+        image::RgbaImage::new(16, 16)
+            .save(dir.join("faces.png"))
+            .unwrap();
+        image::RgbaImage::new(128, 64)
+            .save(dir.join("doors.png"))
+            .unwrap();
+        let assets = Assets::load(Some(&dir));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(assets.textures.len(), 8);
+        assert!(assets
+            .textures
+            .iter()
+            .all(|texture| texture.len() == TEXTURE_SIZE as usize));
+        assert_eq!((assets.faces.width, assets.faces.height), (72, 256));
+        assert_eq!(assets.doors.len(), 3);
+    }
+
+    #[test]
+    fn replacements_with_more_columns_are_used() {
+        let dir = std::env::temp_dir().join(format!("assets-wide-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        image::RgbaImage::new(640, 64)
+            .save(dir.join("textures.png"))
+            .unwrap();
+        let assets = Assets::load(Some(&dir));
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(assets.textures.len(), 10);
+    }
+}
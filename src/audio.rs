@@ -0,0 +1,595 @@
+use std::f32::consts::PI;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use egui::mutex::Mutex;
+use log::error;
+
+use crate::{geo::Point2, map::TILE_WIDTH, music::*, World, TICKS_PER_SECOND};
+
+pub const SAMPLE_RATE: u32 = 44100;
+/// Stereo frames mixed per game tick when nothing else pulls the samples.
+const FRAMES_PER_TICK: usize = (SAMPLE_RATE / TICKS_PER_SECOND) as usize;
+/// Oldest sounds are cut off once this many play at the same time.
+const MAX_VOICES: usize = 16;
+/// Distance in cells past which sounds can't be heard any more.
+const HEARING_CELLS: f32 = 16.0;
+
+/// The sound effects, synthesized at startup in the spirit of the original's PC speaker sounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sound {
+    Knife,
+    Pistol,
+    MachineGun,
+    ChainGun,
+    GuardShoot,
+    GuardAlert,
+    GuardPain,
+    GuardDeath,
+    PlayerPain,
+    DoorOpen,
+    DoorClose,
+    Locked,
+    PushWall,
+    Switch,
+    Key,
+    Ammo,
+    Health,
+    Treasure,
+    Weapon,
+}
+
+impl Sound {
+    pub const ALL: [Sound; 19] = [
+        Sound::Knife,
+        Sound::Pistol,
+        Sound::MachineGun,
+        Sound::ChainGun,
+        Sound::GuardShoot,
+        Sound::GuardAlert,
+        Sound::GuardPain,
+        Sound::GuardDeath,
+        Sound::PlayerPain,
+        Sound::DoorOpen,
+        Sound::DoorClose,
+        Sound::Locked,
+        Sound::PushWall,
+        Sound::Switch,
+        Sound::Key,
+        Sound::Ammo,
+        Sound::Health,
+        Sound::Treasure,
+        Sound::Weapon,
+    ];
+
+    /// Tones played one after the other to make up the sound.
+    fn tones(&self) -> Vec<Tone> {
+        use Wave::*;
+        match self {
+            Sound::Knife => vec![Tone::new(Noise, 3000.0, 1500.0, 0.06, 0.3)],
+            Sound::Pistol => vec![Tone::new(Noise, 4000.0, 800.0, 0.18, 0.8)],
+            Sound::MachineGun => vec![Tone::new(Noise, 5000.0, 1500.0, 0.1, 0.7)],
+            Sound::ChainGun => vec![Tone::new(Noise, 6000.0, 2000.0, 0.07, 0.7)],
+            Sound::GuardShoot => vec![Tone::new(Noise, 3000.0, 600.0, 0.2, 0.8)],
+            Sound::GuardAlert => vec![
+                Tone::new(Square, 700.0, 700.0, 0.08, 0.5),
+                Tone::new(Square, 500.0, 480.0, 0.14, 0.5),
+            ],
+            Sound::GuardPain => vec![Tone::new(Square, 400.0, 250.0, 0.1, 0.5)],
+            Sound::GuardDeath => vec![Tone::new(Square, 500.0, 60.0, 0.45, 0.5)],
+            Sound::PlayerPain => vec![Tone::new(Square, 180.0, 120.0, 0.12, 0.5)],
+            Sound::DoorOpen => vec![Tone::new(Square, 60.0, 120.0, 0.35, 0.3)],
+            Sound::DoorClose => vec![
+                Tone::new(Square, 120.0, 50.0, 0.3, 0.3),
+                Tone::new(Noise, 300.0, 200.0, 0.05, 0.5),
+            ],
+            Sound::Locked => vec![Tone::new(Square, 90.0, 90.0, 0.15, 0.4)],
+            Sound::PushWall => vec![Tone::new(Noise, 200.0, 100.0, 0.6, 0.5)],
+            Sound::Switch => vec![Tone::new(Square, 300.0, 900.0, 0.3, 0.4)],
+            Sound::Key => vec![Tone::new(Square, 1000.0, 1000.0, 0.08, 0.4)],
+            Sound::Ammo => vec![
+                Tone::new(Square, 1000.0, 1000.0, 0.04, 0.4),
+                Tone::new(Square, 1500.0, 1500.0, 0.06, 0.4),
+            ],
+            Sound::Health => vec![Tone::new(Square, 600.0, 1200.0, 0.15, 0.4)],
+            Sound::Treasure => vec![
+                Tone::new(Square, 800.0, 800.0, 0.05, 0.4),
+                Tone::new(Square, 1200.0, 1200.0, 0.05, 0.4),
+                Tone::new(Square, 1600.0, 1600.0, 0.08, 0.4),
+            ],
+            Sound::Weapon => vec![
+                Tone::new(Square, 400.0, 400.0, 0.06, 0.4),
+                Tone::new(Square, 600.0, 600.0, 0.06, 0.4),
+                Tone::new(Square, 900.0, 900.0, 0.1, 0.4),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Wave {
+    Square,
+    /// Random levels held for a period of the frequency.
+    Noise,
+}
+
+/// A sweep from one frequency to another, fading out towards its end.
+#[derive(Debug, Clone, Copy)]
+struct Tone {
+    wave: Wave,
+    from: f32,
+    to: f32,
+    seconds: f32,
+    volume: f32,
+}
+
+impl Tone {
+    fn new(wave: Wave, from: f32, to: f32, seconds: f32, volume: f32) -> Self {
+        Self {
+            wave,
+            from,
+            to,
+            seconds,
+            volume,
+        }
+    }
+}
+
+/// Mono samples of `tones` played back to back.
+fn synthesize(tones: &[Tone]) -> Vec<f32> {
+    let mut samples = vec![];
+    // Same noise every run, so rendered sound is reproducible
+    let mut noise = 0x1234_5678u32;
+    let mut level = 1.0;
+    for tone in tones {
+        let length = (tone.seconds * SAMPLE_RATE as f32) as usize;
+        let mut phase = 0.0f32;
+        for i in 0..length {
+            let progress = i as f32 / length as f32;
+            let frequency = tone.from + (tone.to - tone.from) * progress;
+            phase += frequency / SAMPLE_RATE as f32;
+            let value = match tone.wave {
+                Wave::Square => {
+                    if phase.fract() < 0.5 {
+                        1.0
+                    } else {
+                        -1.0
+                    }
+                }
+                Wave::Noise => {
+                    if phase >= 1.0 {
+                        noise ^= noise << 13;
+                        noise ^= noise >> 17;
+                        noise ^= noise << 5;
+                        level = (noise & 0xffff) as f32 / 32767.5 - 1.0;
+                    }
+                    level
+                }
+            };
+            phase = phase.fract();
+            samples.push(value * tone.volume * (1.0 - progress));
+        }
+    }
+    samples
+}
+
+/// Left and right volume of a voice.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gains {
+    pub left: f32,
+    pub right: f32,
+}
+
+impl Gains {
+    /// Full volume, straight ahead.
+    pub const CENTER: Gains = Gains {
+        left: std::f32::consts::FRAC_1_SQRT_2,
+        right: std::f32::consts::FRAC_1_SQRT_2,
+    };
+
+    /// Constant power panning of `volume`, from -1 (left) to 1 (right).
+    pub fn panned(volume: f32, pan: f32) -> Self {
+        let angle = (pan.clamp(-1.0, 1.0) + 1.0) * PI / 4.0;
+        Self {
+            left: volume * angle.cos(),
+            right: volume * angle.sin(),
+        }
+    }
+}
+
+/// Where sounds are heard from, the player's `x`, `y` and `theta`.
+#[derive(Debug, Clone, Copy)]
+pub struct Listener {
+    pub position: Point2,
+    pub theta: f32,
+}
+
+impl Listener {
+    /// Gains of a sound coming from `source`, quieter with distance and panned to its side.
+    pub fn gains(&self, source: Point2) -> Gains {
+        let to_source = source - self.position;
+        let distance = to_source.length();
+        let volume = (1.0 - distance / (HEARING_CELLS * TILE_WIDTH)).clamp(0.0, 1.0);
+        if distance < 1.0 {
+            return Gains::panned(volume, 0.0);
+        }
+        // y grows downwards, so a positive angle is to the right
+        let angle = to_source.y.atan2(to_source.x) - self.theta;
+        Gains::panned(volume, angle.sin())
+    }
+}
+
+struct Voice {
+    samples: Arc<[f32]>,
+    position: usize,
+    gains: Gains,
+}
+
+/// Mixes the music and sounds playing into interleaved stereo samples.
+pub struct Mixer {
+    /// Samples of each sound, in `Sound::ALL` order.
+    sounds: Vec<Arc<[f32]>>,
+    voices: Vec<Voice>,
+    music: Option<MusicPlayer>,
+    /// Sound effects volume, from 0 to 1.
+    pub volume: f32,
+    pub music_volume: f32,
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Self {
+            sounds: Sound::ALL
+                .iter()
+                .map(|sound| synthesize(&sound.tones()).into())
+                .collect(),
+            voices: vec![],
+            music: None,
+            volume: 0.8,
+            music_volume: 0.5,
+        }
+    }
+}
+
+impl Mixer {
+    pub fn play(&mut self, sound: Sound, gains: Gains) {
+        if gains.left <= 0.0 && gains.right <= 0.0 {
+            return;
+        }
+        if self.voices.len() >= MAX_VOICES {
+            self.voices.remove(0);
+        }
+        self.voices.push(Voice {
+            samples: self.sounds[sound as usize].clone(),
+            position: 0,
+            gains,
+        });
+    }
+
+    pub fn is_playing(&self) -> bool {
+        !self.voices.is_empty()
+    }
+
+    /// Start `track` from the beginning, unless it's already the one playing.
+    pub fn play_music(&mut self, track: &Track) {
+        if self.music_name() != Some(track.name) {
+            self.music = Some(MusicPlayer::new(track.name, track.imf()));
+        }
+    }
+
+    pub fn stop_music(&mut self) {
+        self.music = None;
+    }
+
+    pub fn music_name(&self) -> Option<&str> {
+        self.music.as_ref().map(|music| music.name.as_str())
+    }
+
+    /// Overwrite `out` with the next interleaved left and right samples.
+    pub fn mix(&mut self, out: &mut [f32]) {
+        out.fill(0.0);
+        if let Some(music) = &mut self.music {
+            music.render(out, self.music_volume);
+        }
+        for voice in &mut self.voices {
+            for (frame, sample) in out
+                .chunks_exact_mut(2)
+                .zip(&voice.samples[voice.position..])
+            {
+                frame[0] += sample * voice.gains.left * self.volume;
+                frame[1] += sample * voice.gains.right * self.volume;
+            }
+            voice.position = (voice.position + out.len() / 2).min(voice.samples.len());
+        }
+        self.voices
+            .retain(|voice| voice.position < voice.samples.len());
+        for sample in out {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+    }
+}
+
+/// Writes 16-bit stereo PCM to a WAV file, keeping the header up to date as it goes.
+pub struct WavWriter {
+    file: BufWriter<File>,
+    frames: u32,
+}
+
+impl WavWriter {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut writer = Self {
+            file: BufWriter::new(File::create(path)?),
+            frames: 0,
+        };
+        writer.write_header()?;
+        Ok(writer)
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let data_size = self.frames * 4;
+        let file = &mut self.file;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(b"RIFF")?;
+        file.write_all(&(36 + data_size).to_le_bytes())?;
+        file.write_all(b"WAVEfmt ")?;
+        file.write_all(&16u32.to_le_bytes())?;
+        // PCM, two channels
+        file.write_all(&1u16.to_le_bytes())?;
+        file.write_all(&2u16.to_le_bytes())?;
+        file.write_all(&SAMPLE_RATE.to_le_bytes())?;
+        file.write_all(&(SAMPLE_RATE * 4).to_le_bytes())?;
+        file.write_all(&4u16.to_le_bytes())?;
+        file.write_all(&16u16.to_le_bytes())?;
+        file.write_all(b"data")?;
+        file.write_all(&data_size.to_le_bytes())?;
+        file.seek(SeekFrom::End(0))?;
+        Ok(())
+    }
+
+    /// Append interleaved stereo `samples`.
+    pub fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        for sample in samples {
+            let value = (sample * i16::MAX as f32) as i16;
+            self.file.write_all(&value.to_le_bytes())?;
+        }
+        self.frames += (samples.len() / 2) as u32;
+        self.write_header()?;
+        self.file.flush()
+    }
+}
+
+#[derive(Debug)]
+pub enum AudioError {
+    /// Built without the `sound` feature.
+    Unsupported,
+    NoDevice,
+    Device(String),
+}
+
+impl fmt::Display for AudioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioError::Unsupported => write!(f, "built without the sound feature"),
+            AudioError::NoDevice => write!(f, "no sound device"),
+            AudioError::Device(err) => write!(f, "sound device error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for AudioError {}
+
+/// Stream feeding the sound card, stopping when dropped.
+#[cfg(feature = "sound")]
+pub type DeviceStream = cpal::Stream;
+#[cfg(not(feature = "sound"))]
+pub type DeviceStream = ();
+
+/// Where mixed samples go.
+pub enum AudioOutput {
+    /// Mixed and thrown away, so sounds still end on time.
+    Null,
+    Wav(WavWriter),
+    /// The sound card pulls samples from the mixer on its own thread.
+    Device,
+}
+
+pub struct Audio {
+    pub mixer: Arc<Mutex<Mixer>>,
+    output: AudioOutput,
+    buffer: Vec<f32>,
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Self {
+            mixer: Arc::default(),
+            output: AudioOutput::Null,
+            buffer: vec![0.0; FRAMES_PER_TICK * 2],
+        }
+    }
+}
+
+impl Audio {
+    /// Record everything played from now on to a WAV file, one tick of sound per game tick.
+    pub fn record_wav(&mut self, path: &Path) -> io::Result<()> {
+        self.output = AudioOutput::Wav(WavWriter::create(path)?);
+        Ok(())
+    }
+
+    /// Play through the default sound card from now on. The stream has to be kept around
+    /// for as long.
+    #[cfg(feature = "sound")]
+    pub fn open_device(&mut self) -> Result<DeviceStream, AudioError> {
+        use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or(AudioError::NoDevice)?;
+        let config = cpal::StreamConfig {
+            channels: 2,
+            sample_rate: cpal::SampleRate(SAMPLE_RATE),
+            buffer_size: cpal::BufferSize::Default,
+        };
+        let mixer = self.mixer.clone();
+        let stream = device
+            .build_output_stream(
+                &config,
+                move |data: &mut [f32], _| mixer.lock().mix(data),
+                |err| error!("audio stream failed: {err}"),
+                None,
+            )
+            .map_err(|err| AudioError::Device(err.to_string()))?;
+        stream
+            .play()
+            .map_err(|err| AudioError::Device(err.to_string()))?;
+        self.output = AudioOutput::Device;
+        Ok(stream)
+    }
+
+    #[cfg(not(feature = "sound"))]
+    pub fn open_device(&mut self) -> Result<DeviceStream, AudioError> {
+        Err(AudioError::Unsupported)
+    }
+
+    pub fn play(&self, sound: Sound, gains: Gains) {
+        self.mixer.lock().play(sound, gains);
+    }
+
+    pub fn play_music(&self, track: &Track) {
+        self.mixer.lock().play_music(track);
+    }
+
+    pub fn stop_music(&self) {
+        self.mixer.lock().stop_music();
+    }
+
+    pub fn music_name(&self) -> Option<String> {
+        self.mixer.lock().music_name().map(str::to_string)
+    }
+
+    pub fn music_volume(&self) -> f32 {
+        self.mixer.lock().music_volume
+    }
+
+    pub fn set_music_volume(&self, volume: f32) {
+        self.mixer.lock().music_volume = volume;
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.mixer.lock().volume
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        self.mixer.lock().volume = volume;
+    }
+
+    /// Mix one tick's worth of sound, unless the sound card does that itself.
+    pub fn tick(&mut self) {
+        match &mut self.output {
+            AudioOutput::Null => self.mixer.lock().mix(&mut self.buffer),
+            AudioOutput::Wav(writer) => {
+                self.mixer.lock().mix(&mut self.buffer);
+                if let Err(err) = writer.write(&self.buffer) {
+                    error!("writing sound failed: {err}");
+                    self.output = AudioOutput::Null;
+                }
+            }
+            AudioOutput::Device => {}
+        }
+    }
+}
+
+impl World {
+    pub fn listener(&self) -> Listener {
+        Listener {
+            position: Point2::new(self.x, self.y),
+            theta: self.theta,
+        }
+    }
+
+    /// Play `sound` coming from `source`, or from the player themselves for `None`.
+    pub fn play_sound(&self, sound: Sound, source: Option<Point2>) {
+        let gains = match source {
+            Some(source) => self.listener().gains(source),
+            None => Gains::CENTER,
+        };
+        self.audio.play(sound, gains);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// FNV-1a, enough to notice any change in rendered bytes.
+    fn hash(bytes: &[u8]) -> u64 {
+        bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100_0000_01b3)
+        })
+    }
+
+    fn listener() -> Listener {
+        Listener {
+            position: Point2::new(100.0, 100.0),
+            theta: 0.0,
+        }
+    }
+
+    #[test]
+    fn panning_keeps_the_power_and_picks_a_side() {
+        let left = Gains::panned(0.5, -1.0);
+        assert!((left.left - 0.5).abs() < 1e-6 && left.right.abs() < 1e-6);
+        let right = Gains::panned(0.5, 1.0);
+        assert!(right.left.abs() < 1e-6 && (right.right - 0.5).abs() < 1e-6);
+        assert_eq!(Gains::panned(1.0, 0.0), Gains::CENTER);
+        for pan in [-0.7, -0.2, 0.3, 0.9] {
+            let gains = Gains::panned(0.5, pan);
+            assert!((gains.left.powi(2) + gains.right.powi(2) - 0.25).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn sources_pan_to_their_side() {
+        // Facing along x with y growing downwards, +y is on the right
+        let right = listener().gains(Point2::new(100.0, 140.0));
+        assert!(right.right > right.left);
+        let left = listener().gains(Point2::new(100.0, 60.0));
+        assert!(left.left > left.right);
+        let ahead = listener().gains(Point2::new(140.0, 100.0));
+        assert!((ahead.left - ahead.right).abs() < 1e-6);
+    }
+
+    #[test]
+    fn volume_drops_with_distance() {
+        let volume = |distance: f32| {
+            let gains = listener().gains(Point2::new(100.0 + distance, 100.0));
+            gains.left + gains.right
+        };
+        assert!(volume(20.0) > volume(80.0));
+        assert!(volume(80.0) > volume(160.0));
+        assert_eq!(volume(HEARING_CELLS * TILE_WIDTH + 1.0), 0.0);
+    }
+
+    #[test]
+    fn wav_render_is_stable() {
+        let mut mixer = Mixer::default();
+        mixer.play(Sound::Pistol, Gains::panned(1.0, -0.5));
+        mixer.play(Sound::GuardAlert, Gains::panned(0.6, 0.8));
+        let path = std::env::temp_dir().join(format!("mix-{}.wav", std::process::id()));
+        let mut wav = WavWriter::create(&path).unwrap();
+        let mut buffer = vec![0.0; FRAMES_PER_TICK * 2];
+        for _ in 0..20 {
+            mixer.mix(&mut buffer);
+            wav.write(&buffer).unwrap();
+        }
+        drop(wav);
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(bytes.len(), 44 + 20 * FRAMES_PER_TICK * 4);
+        assert_eq!(&bytes[..4], b"RIFF");
+        assert_eq!(hash(&bytes), 18430559510479022653);
+    }
+}
@@ -0,0 +1,245 @@
+use std::f32::consts::FRAC_PI_2;
+
+use glam::Vec2;
+use palette::Srgb;
+
+use crate::{
+    doors::Lock, geo::*, graphics::*, map::*, objects::ObjectKind, World, VIEW_HEIGHT, WIDTH,
+};
+
+/// Size of the corner minimap.
+const CORNER_SIZE: Vec2 = Vec2::new(160.0, 120.0);
+const CORNER_MARGIN: f32 = 8.0;
+pub const MIN_ZOOM: f32 = 2.0;
+pub const MAX_ZOOM: f32 = 32.0;
+/// Length of the field of view lines, in cells.
+const FOV_CELLS: f32 = 4.0;
+
+const BACKGROUND_COLOR: Srgb = Srgb::new(0.0, 0.0, 0.0);
+const BORDER_COLOR: Srgb = Srgb::new(0.2, 0.45, 0.75);
+const WALL_COLOR: Srgb = Srgb::new(0.8, 0.8, 0.8);
+const ELEVATOR_COLOR: Srgb = Srgb::new(0.8, 0.6, 0.3);
+const DOOR_COLOR: Srgb = Srgb::new(0.4, 0.7, 1.0);
+const GOLD_COLOR: Srgb = Srgb::new(1.0, 0.8, 0.1);
+const SILVER_COLOR: Srgb = Srgb::new(0.75, 0.8, 0.85);
+const ITEM_COLOR: Srgb = Srgb::new(0.2, 0.8, 0.2);
+const ENEMY_COLOR: Srgb = Srgb::new(1.0, 0.2, 0.2);
+const PLAYER_COLOR: Srgb = Srgb::new(1.0, 1.0, 1.0);
+const FOV_COLOR: Srgb = Srgb::new(0.5, 0.5, 0.3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutomapMode {
+    Off,
+    /// Small map in the top right corner of the view.
+    Corner,
+    /// Map covering the whole view.
+    Full,
+}
+
+impl AutomapMode {
+    pub fn next(self) -> Self {
+        match self {
+            AutomapMode::Off => AutomapMode::Corner,
+            AutomapMode::Corner => AutomapMode::Full,
+            AutomapMode::Full => AutomapMode::Off,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AutomapMode::Off => "Off",
+            AutomapMode::Corner => "Corner",
+            AutomapMode::Full => "Full",
+        }
+    }
+}
+
+/// How the map overlay is shown.
+#[derive(Debug, Clone)]
+pub struct Automap {
+    pub mode: AutomapMode,
+    /// Screen pixels per map cell.
+    pub zoom: f32,
+    /// Turn the map with the player so that forward is always up.
+    pub rotate: bool,
+    pub show_enemies: bool,
+}
+
+impl Default for Automap {
+    fn default() -> Self {
+        Self {
+            mode: AutomapMode::Off,
+            zoom: 8.0,
+            rotate: false,
+            show_enemies: false,
+        }
+    }
+}
+
+impl Automap {
+    pub fn zoom_in(&mut self) {
+        self.zoom = (self.zoom * 1.25).min(MAX_ZOOM);
+    }
+
+    pub fn zoom_out(&mut self) {
+        self.zoom = (self.zoom / 1.25).max(MIN_ZOOM);
+    }
+}
+
+impl World {
+    pub fn draw_automap(&self, frame: &mut [u8]) {
+        let area = match self.automap.mode {
+            AutomapMode::Off => return,
+            AutomapMode::Corner => Rect::new(
+                WIDTH as f32 - CORNER_SIZE.x - CORNER_MARGIN,
+                CORNER_MARGIN,
+                CORNER_SIZE.x,
+                CORNER_SIZE.y,
+            ),
+            AutomapMode::Full => Rect::new(0.0, 0.0, WIDTH as f32, VIEW_HEIGHT as f32),
+        };
+        ColorRect::new(area.x, area.y, area.width, area.height, BACKGROUND_COLOR).draw(frame);
+
+        let player = Vec2::new(self.x, self.y);
+        let scale = Vec2::new(
+            self.automap.zoom / TILE_WIDTH,
+            self.automap.zoom / TILE_HEIGHT,
+        );
+        let center = area.center();
+        let rotation = if self.automap.rotate {
+            Vec2::from_angle(-self.theta - FRAC_PI_2)
+        } else {
+            Vec2::X
+        };
+        let to_screen = |point: Point2| center + rotation.rotate((point - player) * scale);
+        let line = |frame: &mut [u8], start: Point2, end: Point2, color: Srgb| {
+            if let Some((start, end)) = area.clip_line(to_screen(start), to_screen(end)) {
+                ColorLine::new(start, end, color).draw(frame);
+            }
+        };
+        let cell = |x: usize, y: usize| Vec2::new(x as f32 * TILE_WIDTH, y as f32 * TILE_HEIGHT);
+        let is_open = |x: usize, y: usize| {
+            let tile = self.map.tile(x, y);
+            tile == 0 || is_door(tile)
+        };
+
+        for y in 0..self.map.height {
+            for x in 0..self.map.width {
+                if !self.is_mapped(x, y) {
+                    continue;
+                }
+                let tile = self.map.tile(x, y);
+                if tile == 0 {
+                    continue;
+                }
+                if let Some(door) = self.door_at(x, y) {
+                    let color = match door.lock {
+                        Lock::None => DOOR_COLOR,
+                        Lock::Gold => GOLD_COLOR,
+                        Lock::Silver => SILVER_COLOR,
+                    };
+                    // Doors run between the walls on either side
+                    let across_x = x > 0 && !is_open(x - 1, y);
+                    let (start, end) = if across_x {
+                        (Vec2::new(0.0, 0.5), Vec2::new(1.0, 0.5))
+                    } else {
+                        (Vec2::new(0.5, 0.0), Vec2::new(0.5, 1.0))
+                    };
+                    let size = Vec2::new(TILE_WIDTH, TILE_HEIGHT);
+                    line(
+                        frame,
+                        cell(x, y) + start * size,
+                        cell(x, y) + end * size,
+                        color,
+                    );
+                    continue;
+                }
+                let color = if is_elevator_switch(tile) {
+                    ELEVATOR_COLOR
+                } else {
+                    WALL_COLOR
+                };
+                // Only outline the faces that can be seen from the floor
+                if y > 0 && is_open(x, y - 1) {
+                    line(frame, cell(x, y), cell(x + 1, y), color);
+                }
+                if y + 1 < self.map.height && is_open(x, y + 1) {
+                    line(frame, cell(x, y + 1), cell(x + 1, y + 1), color);
+                }
+                if x > 0 && is_open(x - 1, y) {
+                    line(frame, cell(x, y), cell(x, y + 1), color);
+                }
+                if x + 1 < self.map.width && is_open(x + 1, y) {
+                    line(frame, cell(x + 1, y), cell(x + 1, y + 1), color);
+                }
+            }
+        }
+
+        let radius = (self.automap.zoom / 4.0).max(1.0);
+        let mut circle = |point: Point2, color: Srgb| {
+            let point = to_screen(point);
+            if area.contains_point(point) {
+                ColorCircle::new(point, radius, color).draw(frame);
+            }
+        };
+        for object in &self.objects {
+            let Some((x, y)) = self.map.cell_at(object.x, object.y) else {
+                continue;
+            };
+            if !self.is_mapped(x, y) {
+                continue;
+            }
+            let color = match object.kind {
+                ObjectKind::GoldKey => GOLD_COLOR,
+                ObjectKind::SilverKey => SILVER_COLOR,
+                _ => ITEM_COLOR,
+            };
+            circle(Point2::new(object.x, object.y), color);
+        }
+        if self.automap.show_enemies {
+            for actor in self.actors.iter().filter(|actor| actor.is_alive()) {
+                circle(actor.position(), ENEMY_COLOR);
+            }
+        }
+
+        let fov = self.fov.to_radians() / 2.0;
+        let reach = Vec2::new(TILE_WIDTH, TILE_HEIGHT) * FOV_CELLS;
+        for angle in [self.theta - fov, self.theta + fov] {
+            line(
+                frame,
+                player,
+                player + Vec2::from_angle(angle) * reach,
+                FOV_COLOR,
+            );
+        }
+        // Arrow pointing where the player is looking
+        let size = Vec2::new(TILE_WIDTH, TILE_HEIGHT) * 0.4;
+        let forward = Vec2::from_angle(self.theta);
+        let side = forward.perp();
+        let tip = player + forward * size;
+        let left = player - forward * size + side * size * 0.6;
+        let right = player - forward * size - side * size * 0.6;
+        line(frame, tip, left, PLAYER_COLOR);
+        line(frame, left, right, PLAYER_COLOR);
+        line(frame, right, tip, PLAYER_COLOR);
+
+        if self.automap.mode == AutomapMode::Full {
+            let explored = format!("EXPLORED {}%", self.explored_percent());
+            Text::new(&self.assets.font, 8.0, 8.0, &explored)
+                .scale(2.0)
+                .color(WALL_COLOR)
+                .draw(frame);
+        }
+        if self.automap.mode == AutomapMode::Corner {
+            let [top_left, top_right, bottom_right, bottom_left] = area.points();
+            for (start, end) in [
+                (top_left, top_right),
+                (top_right, bottom_right),
+                (bottom_left, bottom_right),
+                (top_left, bottom_left),
+            ] {
+                ColorLine::new(start, end, BORDER_COLOR).draw(frame);
+            }
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{hud::Message, player::*, World, MAX_AMMO};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cheat {
+    /// Walk through walls and doors.
+    Noclip,
+    /// Ignore damage.
+    God,
+    /// Show the whole level on the automap.
+    RevealMap,
+    /// Stop enemies from doing anything.
+    FreezeAi,
+}
+
+impl Cheat {
+    pub const ALL: [Cheat; 4] = [Cheat::Noclip, Cheat::God, Cheat::RevealMap, Cheat::FreezeAi];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Cheat::Noclip => "Noclip",
+            Cheat::God => "God mode",
+            Cheat::RevealMap => "Reveal map",
+            Cheat::FreezeAi => "Freeze AI",
+        }
+    }
+}
+
+/// Cheats and debug toggles currently on.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cheats {
+    pub noclip: bool,
+    pub god: bool,
+    pub reveal_map: bool,
+    pub freeze_ai: bool,
+}
+
+impl Cheats {
+    pub fn is_on(&self, cheat: Cheat) -> bool {
+        match cheat {
+            Cheat::Noclip => self.noclip,
+            Cheat::God => self.god,
+            Cheat::RevealMap => self.reveal_map,
+            Cheat::FreezeAi => self.freeze_ai,
+        }
+    }
+
+    fn flag_mut(&mut self, cheat: Cheat) -> &mut bool {
+        match cheat {
+            Cheat::Noclip => &mut self.noclip,
+            Cheat::God => &mut self.god,
+            Cheat::RevealMap => &mut self.reveal_map,
+            Cheat::FreezeAi => &mut self.freeze_ai,
+        }
+    }
+}
+
+impl World {
+    /// Turn a cheat on or off. Turning one on marks the game as cheated for good.
+    pub fn set_cheat(&mut self, cheat: Cheat, on: bool) {
+        if self.refuse_while_recording("use cheats") {
+            return;
+        }
+        *self.cheats.flag_mut(cheat) = on;
+        if on {
+            self.stats.cheated = true;
+        }
+        let state = if on { "on" } else { "off" };
+        self.message = Some(Message::new(format!("{} {state}", cheat.name())));
+    }
+
+    pub fn toggle_cheat(&mut self, cheat: Cheat) {
+        self.set_cheat(cheat, !self.cheats.is_on(cheat));
+    }
+
+    /// The original's M-L-I cheat: full health and ammo, both keys and every weapon,
+    /// paid for with the score.
+    pub fn give_all(&mut self) {
+        if self.refuse_while_recording("use cheats") {
+            return;
+        }
+        self.stats = PlayerStats {
+            score: 0,
+            health: 100,
+            ammo: MAX_AMMO,
+            weapon: Weapon::ChainGun,
+            best_weapon: Weapon::ChainGun,
+            keys: Keys {
+                gold: true,
+                silver: true,
+            },
+            cheated: true,
+            ..self.stats.clone()
+        };
+        self.message = Some(Message::new(
+            "You now have 100% health, 99 ammo, both keys and every weapon. \
+             Your score is gone though!"
+                .to_string(),
+        ));
+    }
+}
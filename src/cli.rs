@@ -0,0 +1,185 @@
+use std::fs;
+use std::path::PathBuf;
+
+use clap::Parser;
+use log::LevelFilter;
+
+use wolfenstein_rs::{
+    difficulty::Difficulty, generator::GeneratorOptions, levels::LEVELS, map::Map, rng::time_seed,
+    World, HEIGHT, WIDTH,
+};
+
+/// Command line of the game. Starting anything but the main menu, with a level, map,
+/// difficulty or demo, skips the menu.
+#[derive(Debug, Parser)]
+#[command(version, about = "Very poorly written Wolfenstein remake in Rust.")]
+pub struct Args {
+    /// Level to start on, like `e1m2`
+    #[arg(long, value_parser = parse_level)]
+    pub level: Option<usize>,
+
+    /// Map file to play instead of a built-in level, see `assets/maps` for the format
+    #[arg(long, value_name = "FILE", value_parser = parse_map, conflicts_with = "level")]
+    pub map: Option<Map>,
+
+    /// Play a generated level, with an optional seed and size like `48x48`
+    #[arg(
+        long,
+        value_name = "SEED|WIDTHxHEIGHT",
+        num_args = 0..=2,
+        conflicts_with_all = ["level", "map"],
+    )]
+    pub random_level: Option<Vec<String>>,
+
+    /// Skill: baby, easy, medium or hard
+    #[arg(long, value_parser = parse_difficulty)]
+    pub difficulty: Option<Difficulty>,
+
+    /// Size of the window, like `1280x720`
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_size)]
+    pub window_size: Option<(u32, u32)>,
+
+    /// Share of the screen columns rays are cast for, from 0.1 to 1. Kept in the options
+    #[arg(long, value_parser = parse_render_scale)]
+    pub render_scale: Option<f32>,
+
+    /// Start in borderless full screen
+    #[arg(long)]
+    pub fullscreen: bool,
+
+    /// Record a demo of the game, written to FILE on quitting. Demos are only of built-in
+    /// levels
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["play_demo", "map", "random_level"],
+    )]
+    pub record_demo: Option<PathBuf>,
+
+    /// Play back a demo recorded earlier
+    #[arg(long, value_name = "FILE")]
+    pub play_demo: Option<PathBuf>,
+
+    /// Save a screenshot to FILE and quit without opening a window. With --play-demo the
+    /// whole demo is played first, otherwise --ticks ticks pass standing still
+    #[arg(long, value_name = "FILE")]
+    pub screenshot: Option<PathBuf>,
+
+    /// Ticks to run before a --screenshot
+    #[arg(long, default_value_t = 0, requires = "screenshot")]
+    pub ticks: u32,
+
+    /// Directory to load textures, sprites and the font from instead of the built-in
+    /// ones, using the same file names as `assets`
+    #[arg(long, value_name = "DIR")]
+    pub assets: Option<PathBuf>,
+
+    /// Record the sound to a WAV file instead of playing it
+    #[arg(long, value_name = "FILE")]
+    pub wav: Option<PathBuf>,
+
+    /// Log messages up to LEVEL: off, error, warn, info, debug or trace. Defaults to
+    /// `RUST_LOG`
+    #[arg(long, value_name = "LEVEL")]
+    pub log_level: Option<LevelFilter>,
+}
+
+impl Args {
+    /// Generator options for `--random-level`, a new seed each run if none is given.
+    pub fn random_level(&self) -> Option<GeneratorOptions> {
+        let args = self.random_level.as_ref()?;
+        let mut options = GeneratorOptions {
+            seed: time_seed(),
+            ..Default::default()
+        };
+        for arg in args {
+            if let Ok((width, height)) = parse_size(arg) {
+                options.width = width as usize;
+                options.height = height as usize;
+            } else if let Ok(seed) = arg.parse() {
+                options.seed = seed;
+            }
+        }
+        Some(options)
+    }
+
+    /// Set up `world` for the game the command line asks for, or open the main menu.
+    pub fn start(&self, world: &mut World) {
+        if let Some(scale) = self.render_scale {
+            world.render_scale = scale;
+        }
+        if let Some(difficulty) = self.difficulty {
+            world.difficulty = difficulty;
+        }
+        if let Some(path) = &self.play_demo {
+            world.play_demo(path);
+        } else if let Some(options) = self.random_level() {
+            world.random_level(&options);
+        } else if let Some(map) = &self.map {
+            world.new_game(0, time_seed(), world.difficulty);
+            world.start_map(map.clone());
+        } else if self.starts_game() {
+            world.new_game(self.level.unwrap_or(0), time_seed(), world.difficulty);
+        }
+        if self.record_demo.is_some() {
+            world.start_recording(time_seed());
+        }
+        if self.starts_game() {
+            world.menu.started = true;
+        } else {
+            world.open_menu();
+        }
+    }
+
+    /// Whether the game starts right away instead of on the main menu.
+    pub fn starts_game(&self) -> bool {
+        self.level.is_some()
+            || self.map.is_some()
+            || self.random_level.is_some()
+            || self.difficulty.is_some()
+            || self.record_demo.is_some()
+            || self.play_demo.is_some()
+            || self.screenshot.is_some()
+    }
+}
+
+fn parse_level(name: &str) -> Result<usize, String> {
+    LEVELS
+        .iter()
+        .position(|level| level.name() == name.to_lowercase())
+        .ok_or_else(|| {
+            let names: Vec<_> = LEVELS.iter().map(|level| level.name()).collect();
+            format!("no level {name}, try one of {}", names.join(", "))
+        })
+}
+
+fn parse_map(path: &str) -> Result<Map, String> {
+    let source = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    Map::parse(&source).map_err(|err| err.to_string())
+}
+
+fn parse_difficulty(name: &str) -> Result<Difficulty, String> {
+    match name.to_lowercase().as_str() {
+        "baby" | "0" => Ok(Difficulty::Baby),
+        "easy" | "1" => Ok(Difficulty::Easy),
+        "medium" | "2" => Ok(Difficulty::Medium),
+        "hard" | "3" => Ok(Difficulty::Hard),
+        _ => Err("expected baby, easy, medium or hard".to_string()),
+    }
+}
+
+/// A size like `1280x720`.
+fn parse_size(size: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("expected a size like {}x{}", WIDTH * 2, HEIGHT * 2);
+    let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+    let width = width.parse::<u32>().map_err(|_| invalid())?;
+    let height = height.parse::<u32>().map_err(|_| invalid())?;
+    Ok((width, height))
+}
+
+fn parse_render_scale(scale: &str) -> Result<f32, String> {
+    match scale.parse::<f32>() {
+        Ok(scale) if (0.1..=1.0).contains(&scale) => Ok(scale),
+        _ => Err("expected a number from 0.1 to 1".to_string()),
+    }
+}
@@ -0,0 +1,695 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use palette::Srgb;
+
+use crate::{
+    cheats::*, demo::DemoSettings, generator::GeneratorOptions, geo::*, graphics::*,
+    levels::LEVELS, map::*, music::*, player::*, rng::time_seed, World, MAX_AMMO, VIEW_HEIGHT,
+    WIDTH,
+};
+
+/// Lines of output kept around.
+const MAX_LINES: usize = 200;
+const CONSOLE_HEIGHT: f32 = VIEW_HEIGHT as f32 / 2.0;
+const MARGIN: f32 = 4.0;
+const PROMPT: &str = "] ";
+/// Config files can exec others this many levels deep, which stops one from running itself forever.
+const MAX_EXEC_DEPTH: usize = 8;
+
+const BACKGROUND_COLOR: Srgb = Srgb::new(0.05, 0.05, 0.1);
+const BORDER_COLOR: Srgb = Srgb::new(0.8, 0.6, 0.2);
+const TEXT_COLOR: Srgb = Srgb::new(0.85, 0.85, 0.85);
+const INPUT_COLOR: Srgb = Srgb::new(1.0, 1.0, 0.6);
+
+/// How a cvar's value is read and shown. Values are stored as `f32` either way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CvarKind {
+    Float { min: f32, max: f32 },
+    Bool,
+}
+
+/// A setting that can be read and changed from the console and the Settings window.
+pub struct Cvar {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub kind: CvarKind,
+    pub get: fn(&World) -> f32,
+    pub set: fn(&mut World, f32),
+}
+
+impl Cvar {
+    /// Parse a value typed in the console, clamped to the cvar's range. `nan` and `inf`
+    /// aren't accepted.
+    pub fn parse(&self, value: &str) -> Option<f32> {
+        match self.kind {
+            CvarKind::Float { .. } => value
+                .parse::<f32>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .map(|v| self.clamp(v)),
+            CvarKind::Bool => match value {
+                "1" | "on" | "true" => Some(1.0),
+                "0" | "off" | "false" => Some(0.0),
+                _ => None,
+            },
+        }
+    }
+
+    /// `value` brought into the cvar's range, NaN becoming the minimum or off.
+    pub fn clamp(&self, value: f32) -> f32 {
+        match self.kind {
+            CvarKind::Float { min, .. } if value.is_nan() => min,
+            CvarKind::Float { min, max } => value.clamp(min, max),
+            CvarKind::Bool => (value != 0.0 && !value.is_nan()) as u8 as f32,
+        }
+    }
+
+    pub fn format(&self, value: f32) -> String {
+        match self.kind {
+            CvarKind::Float { .. } => format!("{value}"),
+            CvarKind::Bool => (value != 0.0).to_string(),
+        }
+    }
+}
+
+pub const CVARS: &[Cvar] = &[
+    Cvar {
+        name: "fov",
+        description: "horizontal field of view in degrees",
+        kind: CvarKind::Float {
+            min: 30.0,
+            max: 150.0,
+        },
+        get: |world| world.fov,
+        set: |world, value| world.fov = value,
+    },
+    Cvar {
+        name: "speed",
+        description: "walking speed in world units per tick",
+        kind: CvarKind::Float {
+            min: 0.0,
+            max: 20.0,
+        },
+        get: |world| world.speed,
+        set: |world, value| world.speed = value,
+    },
+    Cvar {
+        name: "rotation_speed",
+        description: "turning speed in radians per tick",
+        kind: CvarKind::Float { min: 0.0, max: 1.0 },
+        get: |world| world.rotation_speed,
+        set: |world, value| world.rotation_speed = value,
+    },
+    Cvar {
+        name: "mouse_sensitivity",
+        description: "radians turned per pixel of mouse movement",
+        kind: CvarKind::Float { min: 0.0, max: 0.1 },
+        get: |world| world.mouse_sensitivity,
+        set: |world, value| world.mouse_sensitivity = value,
+    },
+    Cvar {
+        name: "max_pitch",
+        description: "furthest the view looks up or down in degrees, 0 to keep it level",
+        kind: CvarKind::Float {
+            min: 0.0,
+            max: 30.0,
+        },
+        get: |world| world.max_pitch,
+        set: |world, value| world.max_pitch = value,
+    },
+    Cvar {
+        name: "fog",
+        description: "distance in cells at which walls fade to black, 0 for none",
+        kind: CvarKind::Float {
+            min: 0.0,
+            max: 64.0,
+        },
+        get: |world| world.fog,
+        set: |world, value| world.fog = value,
+    },
+    Cvar {
+        name: "render_scale",
+        description: "share of the screen columns rays are cast for",
+        kind: CvarKind::Float { min: 0.1, max: 1.0 },
+        get: |world| world.render_scale,
+        set: |world, value| world.render_scale = value,
+    },
+    Cvar {
+        name: "view_bob",
+        description: "move the view and weapon with your steps, off for less motion",
+        kind: CvarKind::Bool,
+        get: |world| world.view_bob as u8 as f32,
+        set: |world, value| world.view_bob = value != 0.0,
+    },
+    Cvar {
+        name: "bob_strength",
+        description: "how much the view bobs up and down while walking",
+        kind: CvarKind::Float { min: 0.0, max: 2.0 },
+        get: |world| world.bob_strength,
+        set: |world, value| world.bob_strength = value,
+    },
+    Cvar {
+        name: "sway_strength",
+        description: "how much the weapon sways while walking",
+        kind: CvarKind::Float { min: 0.0, max: 2.0 },
+        get: |world| world.sway_strength,
+        set: |world, value| world.sway_strength = value,
+    },
+    Cvar {
+        name: "volume",
+        description: "sound effects volume",
+        kind: CvarKind::Float { min: 0.0, max: 1.0 },
+        get: |world| world.audio.volume(),
+        set: |world, value| world.audio.set_volume(value),
+    },
+    Cvar {
+        name: "music_volume",
+        description: "music volume",
+        kind: CvarKind::Float { min: 0.0, max: 1.0 },
+        get: |world| world.audio.music_volume(),
+        set: |world, value| world.audio.set_music_volume(value),
+    },
+    Cvar {
+        name: "automap_enemies",
+        description: "show enemies on the automap",
+        kind: CvarKind::Bool,
+        get: |world| world.automap.show_enemies as u8 as f32,
+        set: |world, value| world.automap.show_enemies = value != 0.0,
+    },
+];
+
+/// A console command, returning the text to print or an error.
+struct Command {
+    name: &'static str,
+    usage: &'static str,
+    /// Refused while recording a demo, changing the game other than through the controls.
+    changes_game: bool,
+    run: fn(&mut World, &[&str]) -> Result<String, String>,
+    /// Values the first argument can be completed to.
+    arguments: fn() -> Vec<String>,
+}
+
+const GIVE_ITEMS: [&str; 5] = ["all", "health", "ammo", "keys", "weapons"];
+
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        usage: "help",
+        changes_game: false,
+        run: |_, _| {
+            let commands: Vec<_> = COMMANDS.iter().map(|command| command.usage).collect();
+            let cvars: Vec<_> = CVARS.iter().map(|cvar| cvar.name).collect();
+            Ok(format!(
+                "commands: {}\ncvars: {} (type a name to see its value, add one to set it)",
+                commands.join(", "),
+                cvars.join(", ")
+            ))
+        },
+        arguments: Vec::new,
+    },
+    Command {
+        name: "cvarlist",
+        usage: "cvarlist",
+        changes_game: false,
+        run: |world, _| {
+            let lines: Vec<_> = CVARS
+                .iter()
+                .map(|cvar| {
+                    let value = cvar.format((cvar.get)(world));
+                    format!("{} {value} - {}", cvar.name, cvar.description)
+                })
+                .collect();
+            Ok(lines.join("\n"))
+        },
+        arguments: Vec::new,
+    },
+    Command {
+        name: "clear",
+        usage: "clear",
+        changes_game: false,
+        run: |world, _| {
+            world.console.lines.clear();
+            Ok(String::new())
+        },
+        arguments: Vec::new,
+    },
+    Command {
+        name: "map",
+        usage: "map <e1m1..|random> [seed]",
+        changes_game: true,
+        run: |world, args| match args {
+            ["random", seed @ ..] => {
+                let seed = match seed.first() {
+                    Some(seed) => seed.parse().map_err(|_| format!("invalid seed {seed}"))?,
+                    None => time_seed(),
+                };
+                world.random_level(&GeneratorOptions {
+                    seed,
+                    ..Default::default()
+                });
+                Ok(format!("random level {seed}"))
+            }
+            [name, ..] => {
+                let index = LEVELS
+                    .iter()
+                    .position(|level| level.name() == name.to_lowercase())
+                    .ok_or(format!("no level called {name}"))?;
+                world.load_level(index);
+                Ok(format!("loaded {}", LEVELS[index].name()))
+            }
+            [] => Err("usage: map <e1m1..|random> [seed]".to_string()),
+        },
+        arguments: || {
+            LEVELS
+                .iter()
+                .map(|level| level.name())
+                .chain(["random".to_string()])
+                .collect()
+        },
+    },
+    Command {
+        name: "music",
+        usage: "music [track|off]",
+        changes_game: false,
+        run: |world, args| match args {
+            ["off", ..] => {
+                world.audio.stop_music();
+                Ok("music off".to_string())
+            }
+            [name, ..] => {
+                let track = Track::find(name).ok_or(format!("no track called {name}"))?;
+                world.audio.play_music(track);
+                Ok(format!("playing {}", track.name))
+            }
+            [] => Ok(match world.audio.music_name() {
+                Some(name) => format!("playing {name}"),
+                None => "no music playing".to_string(),
+            }),
+        },
+        arguments: || {
+            TRACKS
+                .iter()
+                .map(|track| track.name.to_string())
+                .chain(["off".to_string()])
+                .collect()
+        },
+    },
+    Command {
+        name: "noclip",
+        usage: "noclip",
+        changes_game: true,
+        run: |world, _| toggle(world, Cheat::Noclip),
+        arguments: Vec::new,
+    },
+    Command {
+        name: "god",
+        usage: "god",
+        changes_game: true,
+        run: |world, _| toggle(world, Cheat::God),
+        arguments: Vec::new,
+    },
+    Command {
+        name: "reveal",
+        usage: "reveal",
+        changes_game: true,
+        run: |world, _| toggle(world, Cheat::RevealMap),
+        arguments: Vec::new,
+    },
+    Command {
+        name: "freeze",
+        usage: "freeze",
+        changes_game: true,
+        run: |world, _| toggle(world, Cheat::FreezeAi),
+        arguments: Vec::new,
+    },
+    Command {
+        name: "give",
+        usage: "give <all|health|ammo|keys|weapons>",
+        changes_game: true,
+        run: |world, args| {
+            let item = *args.first().unwrap_or(&"all");
+            if !GIVE_ITEMS.contains(&item) {
+                return Err(format!("can't give {item}"));
+            }
+            let stats = &mut world.stats;
+            stats.cheated = true;
+            if matches!(item, "all" | "health") {
+                stats.health = 100;
+            }
+            if matches!(item, "all" | "ammo") {
+                stats.ammo = MAX_AMMO;
+            }
+            if matches!(item, "all" | "keys") {
+                stats.keys = Keys {
+                    gold: true,
+                    silver: true,
+                };
+            }
+            if matches!(item, "all" | "weapons") {
+                stats.best_weapon = Weapon::ChainGun;
+                stats.weapon = Weapon::ChainGun;
+            }
+            Ok(format!("gave {item}"))
+        },
+        arguments: || GIVE_ITEMS.iter().map(|item| item.to_string()).collect(),
+    },
+    Command {
+        name: "tp",
+        usage: "tp <x> <y> [angle]",
+        changes_game: true,
+        run: |world, args| {
+            let numbers = args
+                .iter()
+                .map(|arg| arg.parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| "usage: tp <x> <y> [angle]".to_string())?;
+            if let Some(number) = numbers.iter().find(|number| !number.is_finite()) {
+                return Err(format!("invalid number {number}"));
+            }
+            let (x, y) = match numbers[..] {
+                [x, y] | [x, y, _] => (x, y),
+                _ => return Err("usage: tp <x> <y> [angle]".to_string()),
+            };
+            let target = Point2::new((x + 0.5) * TILE_WIDTH, (y + 0.5) * TILE_HEIGHT);
+            if world.map.cell_at(target.x, target.y).is_none() {
+                return Err(format!("{x}, {y} is outside the map"));
+            }
+            if world.will_hit_obstacle(target.x, target.y).is_some() && !world.cheats.noclip {
+                return Err(format!("{x}, {y} is inside a wall"));
+            }
+            world.x = target.x;
+            world.y = target.y;
+            if let Some(angle) = numbers.get(2) {
+                world.theta = angle.to_radians();
+            }
+            Ok(format!("teleported to {x}, {y}"))
+        },
+        arguments: Vec::new,
+    },
+    Command {
+        name: "screenshot",
+        usage: "screenshot [file]",
+        changes_game: false,
+        run: |world, args| {
+            let path = match args.first() {
+                Some(path) => PathBuf::from(path),
+                None => PathBuf::from(format!("screenshot-{}.png", time_seed())),
+            };
+            world.screenshot = Some(path);
+            Ok(String::new())
+        },
+        arguments: Vec::new,
+    },
+    Command {
+        name: "exec",
+        usage: "exec <file>",
+        changes_game: false,
+        run: |world, args| {
+            let path = args.first().ok_or("usage: exec <file>")?;
+            let count = world
+                .exec(Path::new(path))
+                .map_err(|err| format!("{path}: {err}"))?;
+            Ok(format!("ran {count} lines from {path}"))
+        },
+        arguments: Vec::new,
+    },
+];
+
+fn toggle(world: &mut World, cheat: Cheat) -> Result<String, String> {
+    world.toggle_cheat(cheat);
+    let state = if world.cheats.is_on(cheat) {
+        "on"
+    } else {
+        "off"
+    };
+    Ok(format!("{} {state}", cheat.name()))
+}
+
+/// Console commands run at startup, one per line.
+pub fn autoexec_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("wolfenstein-rs")
+        .join("autoexec.cfg")
+}
+
+/// Quake style console dropping down over the top of the view.
+#[derive(Debug, Default)]
+pub struct Console {
+    pub open: bool,
+    pub input: String,
+    /// Output, oldest first.
+    lines: Vec<String>,
+    /// Lines entered so far, oldest first.
+    history: Vec<String>,
+    /// Entry of `history` shown while browsing it with the arrow keys.
+    history_index: Option<usize>,
+    /// Number of config files being run by `exec` right now.
+    exec_depth: usize,
+}
+
+impl Console {
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    pub fn print(&mut self, text: &str) {
+        self.lines.extend(text.lines().map(str::to_string));
+        if self.lines.len() > MAX_LINES {
+            self.lines.drain(..self.lines.len() - MAX_LINES);
+        }
+    }
+
+    pub fn type_char(&mut self, c: char) {
+        if !c.is_control() && c != '`' {
+            self.input.push(c);
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        self.input.pop();
+    }
+
+    pub fn history_up(&mut self) {
+        let index = match self.history_index {
+            Some(index) => index.saturating_sub(1),
+            None => match self.history.len().checked_sub(1) {
+                Some(index) => index,
+                None => return,
+            },
+        };
+        self.history_index = Some(index);
+        self.input = self.history[index].clone();
+    }
+
+    pub fn history_down(&mut self) {
+        let Some(index) = self.history_index else {
+            return;
+        };
+        if index + 1 < self.history.len() {
+            self.history_index = Some(index + 1);
+            self.input = self.history[index + 1].clone();
+        } else {
+            self.history_index = None;
+            self.input.clear();
+        }
+    }
+
+    /// Complete the word being typed: the command or cvar name, or a command's first argument.
+    pub fn complete(&mut self) {
+        let words: Vec<&str> = self.input.split(' ').collect();
+        let candidates: Vec<String> = match words[..] {
+            [word] => COMMANDS
+                .iter()
+                .map(|command| command.name)
+                .chain(CVARS.iter().map(|cvar| cvar.name))
+                .filter(|name| name.starts_with(word))
+                .map(str::to_string)
+                .collect(),
+            [name, word] => match COMMANDS.iter().find(|command| command.name == name) {
+                Some(command) => (command.arguments)()
+                    .into_iter()
+                    .filter(|argument| argument.starts_with(word))
+                    .collect(),
+                None => vec![],
+            },
+            _ => vec![],
+        };
+        let Some(first) = candidates.first() else {
+            return;
+        };
+        let prefix = match self.input.rfind(' ') {
+            Some(space) => self.input[..=space].to_string(),
+            None => String::new(),
+        };
+        if candidates.len() == 1 {
+            self.input = format!("{prefix}{first} ");
+            return;
+        }
+        // Fill in as much as all candidates share and list them
+        let common = candidates.iter().fold(first.clone(), |common, candidate| {
+            common
+                .chars()
+                .zip(candidate.chars())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect()
+        });
+        self.input = format!("{prefix}{common}");
+        self.print(&candidates.join("  "));
+    }
+
+    /// Take the typed line, remembering it in the history.
+    fn submit(&mut self) -> String {
+        let line = std::mem::take(&mut self.input);
+        self.history_index = None;
+        if !line.trim().is_empty() && self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        self.print(&format!("{PROMPT}{line}"));
+        line
+    }
+}
+
+impl World {
+    /// Run the line typed in the console.
+    pub fn submit_console(&mut self) {
+        let line = self.console.submit();
+        self.execute(&line);
+    }
+
+    /// Run a console command or read or set a cvar, printing the outcome to the console.
+    pub fn execute(&mut self, line: &str) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((&name, args)) = words.split_first() else {
+            return;
+        };
+        let result = if let Some(command) = COMMANDS.iter().find(|command| command.name == name) {
+            if command.changes_game && self.demo.is_recording() {
+                Err(format!("can't run {name} while recording a demo"))
+            } else {
+                (command.run)(self, args)
+            }
+        } else if let Some(cvar) = CVARS.iter().find(|cvar| cvar.name == name) {
+            match args.first() {
+                Some(_) if self.demo.is_recording() && DemoSettings::CVARS.contains(&name) => {
+                    Err(format!("can't change {name} while recording a demo"))
+                }
+                Some(value) => match cvar.parse(value) {
+                    Some(value) => {
+                        (cvar.set)(self, value);
+                        Ok(String::new())
+                    }
+                    None => Err(format!("invalid value {value} for {name}")),
+                },
+                None => Ok(format!(
+                    "{name} is {} - {}",
+                    cvar.format((cvar.get)(self)),
+                    cvar.description
+                )),
+            }
+        } else {
+            Err(format!("unknown command {name}"))
+        };
+        match result {
+            Ok(text) => self.console.print(&text),
+            Err(err) => self.console.print(&err),
+        }
+    }
+
+    /// Run every line of a config file, skipping blank lines and `//` comments.
+    pub fn exec(&mut self, path: &Path) -> io::Result<usize> {
+        if self.console.exec_depth == MAX_EXEC_DEPTH {
+            return Err(io::Error::other(format!(
+                "exec nested more than {MAX_EXEC_DEPTH} deep"
+            )));
+        }
+        let source = fs::read_to_string(path)?;
+        let lines: Vec<&str> = source
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("//"))
+            .collect();
+        self.console.exec_depth += 1;
+        for line in &lines {
+            self.execute(line);
+        }
+        self.console.exec_depth -= 1;
+        Ok(lines.len())
+    }
+
+    pub fn draw_console(&self, frame: &mut [u8]) {
+        if !self.console.open {
+            return;
+        }
+        ColorRect::new(0.0, 0.0, WIDTH as f32, CONSOLE_HEIGHT, BACKGROUND_COLOR).draw(frame);
+        ColorRect::new(0.0, CONSOLE_HEIGHT, WIDTH as f32, 1.0, BORDER_COLOR).draw(frame);
+
+        let font = &self.assets.font;
+        let line_height = font.line_height() as f32;
+        let input_y = CONSOLE_HEIGHT - MARGIN - line_height;
+        let input = format!("{PROMPT}{}_", self.console.input);
+        Text::new(font, MARGIN, input_y, &input)
+            .color(INPUT_COLOR)
+            .draw(frame);
+        let rows = ((input_y - MARGIN) / line_height) as usize;
+        let max_width = Some(WIDTH as usize - 2 * MARGIN as usize);
+        let wrapped: Vec<String> = self
+            .console
+            .lines
+            .iter()
+            .flat_map(|line| font.wrap(line, max_width, false))
+            .collect();
+        for (row, line) in wrapped.iter().rev().take(rows).enumerate() {
+            let y = input_y - (row + 1) as f32 * line_height;
+            Text::new(font, MARGIN, y, line)
+                .color(TEXT_COLOR)
+                .draw(frame);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::Assets;
+
+    #[test]
+    fn exec_stops_a_file_running_itself() {
+        let path = std::env::temp_dir().join(format!("exec-self-{}.cfg", std::process::id()));
+        fs::write(&path, format!("exec {}\n", path.display())).unwrap();
+        let mut world = World::new(Assets::load(None));
+        assert!(world.exec(&path).is_ok());
+        fs::remove_file(&path).unwrap();
+        assert!(world
+            .console
+            .lines
+            .iter()
+            .any(|line| line.contains(&format!("nested more than {MAX_EXEC_DEPTH} deep"))));
+    }
+
+    #[test]
+    fn cvars_reject_values_that_are_not_finite() {
+        let mut world = World::new(Assets::load(None));
+        let (fov, speed) = (world.fov, world.speed);
+        for line in ["fov nan", "fov inf", "speed -inf", "speed NaN"] {
+            world.execute(line);
+        }
+        assert_eq!((world.fov, world.speed), (fov, speed));
+        for cvar in CVARS {
+            assert!(cvar.clamp(f32::NAN).is_finite(), "{}", cvar.name);
+        }
+    }
+
+    #[test]
+    fn tp_rejects_numbers_that_are_not_finite() {
+        let mut world = World::new(Assets::load(None));
+        let (x, y) = (world.x, world.y);
+        for line in ["tp inf 2", "tp 2 NaN", "tp 2 2 -inf"] {
+            world.execute(line);
+            assert_eq!((world.x, world.y), (x, y), "{line}");
+        }
+    }
+}
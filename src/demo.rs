@@ -0,0 +1,329 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{difficulty::Difficulty, hud::Message, player::Weapon, rng::Rng, Controls, World};
+
+/// Bumped whenever the demo format or anything affecting the simulation changes.
+pub const DEMO_VERSION: u32 = 5;
+
+const FORWARD: u16 = 1 << 0;
+const BACKWARD: u16 = 1 << 1;
+const LEFT: u16 = 1 << 2;
+const RIGHT: u16 = 1 << 3;
+const STRAFE_LEFT: u16 = 1 << 4;
+const STRAFE_RIGHT: u16 = 1 << 5;
+const ACTIVATE: u16 = 1 << 6;
+const FIRE: u16 = 1 << 7;
+/// The selected weapon's number is stored in the bits above the buttons.
+const WEAPON_SHIFT: u16 = 8;
+const WEAPON_MASK: u16 = 0b111;
+const CROUCH: u16 = 1 << 11;
+const LOOK_UP: u16 = 1 << 12;
+const LOOK_DOWN: u16 = 1 << 13;
+
+#[derive(Debug)]
+pub enum DemoError {
+    Io(std::io::Error),
+    Format(serde_json::Error),
+    /// The demo was recorded by a different version of the game.
+    Version(u32),
+}
+
+impl fmt::Display for DemoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DemoError::Io(err) => write!(f, "{err}"),
+            DemoError::Format(err) => write!(f, "invalid demo file: {err}"),
+            DemoError::Version(version) => write!(
+                f,
+                "demo version {version} is not supported (expected {DEMO_VERSION})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DemoError {}
+
+impl From<std::io::Error> for DemoError {
+    fn from(err: std::io::Error) -> Self {
+        DemoError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for DemoError {
+    fn from(err: serde_json::Error) -> Self {
+        DemoError::Format(err)
+    }
+}
+
+/// The controls of a single tick, packed: the buttons and the mouse movement.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DemoTick(pub u16, pub f32, pub f32);
+
+impl DemoTick {
+    pub fn from_controls(controls: &Controls) -> Self {
+        let buttons = [
+            (controls.forward, FORWARD),
+            (controls.backward, BACKWARD),
+            (controls.left, LEFT),
+            (controls.right, RIGHT),
+            (controls.strafe_left, STRAFE_LEFT),
+            (controls.strafe_right, STRAFE_RIGHT),
+            (controls.activate, ACTIVATE),
+            (controls.fire, FIRE),
+            (controls.crouch, CROUCH),
+            (controls.look_up, LOOK_UP),
+            (controls.look_down, LOOK_DOWN),
+        ]
+        .iter()
+        .filter(|(held, _)| *held)
+        .fold(0, |bits, (_, bit)| bits | bit);
+        let weapon = controls.weapon.map_or(0, |weapon| weapon as u16 + 1);
+        Self(
+            buttons | weapon << WEAPON_SHIFT,
+            controls.mouse_dx,
+            controls.mouse_dy,
+        )
+    }
+
+    pub fn controls(&self) -> Controls {
+        let Self(bits, mouse_dx, mouse_dy) = *self;
+        Controls {
+            forward: bits & FORWARD != 0,
+            backward: bits & BACKWARD != 0,
+            left: bits & LEFT != 0,
+            right: bits & RIGHT != 0,
+            strafe_left: bits & STRAFE_LEFT != 0,
+            strafe_right: bits & STRAFE_RIGHT != 0,
+            activate: bits & ACTIVATE != 0,
+            fire: bits & FIRE != 0,
+            crouch: bits & CROUCH != 0,
+            look_up: bits & LOOK_UP != 0,
+            look_down: bits & LOOK_DOWN != 0,
+            weapon: Weapon::from_number(((bits >> WEAPON_SHIFT) & WEAPON_MASK) as u32),
+            mouse_dx,
+            mouse_dy,
+            ..Default::default()
+        }
+    }
+}
+
+/// The settings that change how the controls move the player, kept with the demo so it
+/// plays back the same whatever the player's own settings are.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DemoSettings {
+    pub speed: f32,
+    pub rotation_speed: f32,
+    pub mouse_sensitivity: f32,
+    pub max_pitch: f32,
+}
+
+impl DemoSettings {
+    /// Cvars of the settings, which can't be changed while recording.
+    pub const CVARS: [&'static str; 4] =
+        ["speed", "rotation_speed", "mouse_sensitivity", "max_pitch"];
+
+    pub fn from_world(world: &World) -> Self {
+        Self {
+            speed: world.speed,
+            rotation_speed: world.rotation_speed,
+            mouse_sensitivity: world.mouse_sensitivity,
+            max_pitch: world.max_pitch,
+        }
+    }
+
+    pub fn apply(&self, world: &mut World) {
+        world.speed = self.speed;
+        world.rotation_speed = self.rotation_speed;
+        world.mouse_sensitivity = self.mouse_sensitivity;
+        world.max_pitch = self.max_pitch;
+    }
+}
+
+/// A recorded session: where it started, the settings it was played with and the controls
+/// of every tick since.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Demo {
+    pub version: u32,
+    pub level_index: usize,
+    pub seed: u32,
+    pub difficulty: Difficulty,
+    pub settings: DemoSettings,
+    pub ticks: Vec<DemoTick>,
+}
+
+impl Demo {
+    pub fn new(
+        level_index: usize,
+        seed: u32,
+        difficulty: Difficulty,
+        settings: DemoSettings,
+    ) -> Self {
+        Self {
+            version: DEMO_VERSION,
+            level_index,
+            seed,
+            difficulty,
+            settings,
+            ticks: vec![],
+        }
+    }
+
+    pub fn record(&mut self, controls: &Controls) {
+        self.ticks.push(DemoTick::from_controls(controls));
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), DemoError> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, DemoError> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Parse a demo, checking its version before anything else.
+    pub fn from_json(json: &str) -> Result<Self, DemoError> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        let version = value
+            .get("version")
+            .and_then(|version| version.as_u64())
+            .unwrap_or(0) as u32;
+        if version != DEMO_VERSION {
+            return Err(DemoError::Version(version));
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Replay the whole demo on `world` without drawing anything.
+    pub fn run(&self, world: &mut World) {
+        world.new_game(self.level_index, self.seed, self.difficulty);
+        self.settings.apply(world);
+        for tick in &self.ticks {
+            world.update(&tick.controls());
+        }
+    }
+}
+
+/// Whether the world is recording or replaying a demo.
+#[derive(Debug, Default)]
+pub enum DemoState {
+    #[default]
+    Idle,
+    Recording(Demo),
+    /// Demo being played, the index of the next tick and the player's own settings, put
+    /// back once it ends.
+    Playing(Demo, usize, DemoSettings),
+}
+
+impl DemoState {
+    pub fn is_recording(&self) -> bool {
+        matches!(self, DemoState::Recording(_))
+    }
+
+    pub fn is_playing(&self) -> bool {
+        matches!(self, DemoState::Playing(..))
+    }
+}
+
+impl World {
+    /// Restart `level_index` at `difficulty` with fresh stats and the RNG seeded with `seed`.
+    pub fn new_game(&mut self, level_index: usize, seed: u32, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+        self.stats = Default::default();
+        self.face = Default::default();
+        self.cheats = Default::default();
+        self.ticks = 0;
+        self.rng = Rng::new(seed);
+        self.load_level(level_index);
+    }
+
+    /// Restart the current level and record from there. Demos only hold the level index,
+    /// so generated and loaded maps can't be recorded.
+    pub fn start_recording(&mut self, seed: u32) {
+        if self.custom_map {
+            self.message = Some(Message::new(
+                "Demos can only be recorded on built-in levels".to_string(),
+            ));
+            return;
+        }
+        self.new_game(self.level_index, seed, self.difficulty);
+        let settings = DemoSettings::from_world(self);
+        self.demo =
+            DemoState::Recording(Demo::new(self.level_index, seed, self.difficulty, settings));
+    }
+
+    /// Whether a demo is being recorded, in which case `action` is refused with a message:
+    /// anything changing the game other than the controls would make it play back differently.
+    pub fn refuse_while_recording(&mut self, action: &str) -> bool {
+        if self.demo.is_recording() {
+            self.message = Some(Message::new(format!(
+                "Can't {action} while recording a demo"
+            )));
+        }
+        self.demo.is_recording()
+    }
+
+    /// Stop recording and write the demo to `path`.
+    pub fn stop_recording(&mut self, path: &Path) {
+        let DemoState::Recording(demo) = std::mem::take(&mut self.demo) else {
+            return;
+        };
+        let text = match demo.save(path) {
+            Ok(()) => format!("Demo saved ({} ticks)", demo.ticks.len()),
+            Err(err) => format!("Saving demo failed: {err}"),
+        };
+        self.message = Some(Message::new(text));
+    }
+
+    pub fn play_demo(&mut self, path: &Path) {
+        match Demo::load(path) {
+            Ok(demo) => {
+                let own = DemoSettings::from_world(self);
+                self.new_game(demo.level_index, demo.seed, demo.difficulty);
+                demo.settings.apply(self);
+                self.demo = DemoState::Playing(demo, 0, own);
+            }
+            Err(err) => self.message = Some(Message::new(format!("Loading demo failed: {err}"))),
+        }
+    }
+
+    /// Run one tick, taking the controls from the demo being played and recording them if asked to.
+    pub fn tick(&mut self, controls: &Controls) {
+        // Settings changed from the menus are put back for as long as the demo lasts
+        if let DemoState::Recording(demo) | DemoState::Playing(demo, ..) = &self.demo {
+            let settings = demo.settings;
+            if DemoSettings::from_world(self) != settings {
+                settings.apply(self);
+                self.message = Some(Message::new(
+                    "Movement settings can't change during a demo".to_string(),
+                ));
+            }
+        }
+        let controls = match &mut self.demo {
+            DemoState::Playing(demo, next, own) => match demo.ticks.get(*next) {
+                Some(tick) => {
+                    *next += 1;
+                    tick.controls()
+                }
+                None => {
+                    let own = *own;
+                    own.apply(self);
+                    self.demo = DemoState::Idle;
+                    self.message = Some(Message::new("Demo finished".to_string()));
+                    controls.clone()
+                }
+            },
+            DemoState::Recording(demo) => {
+                demo.record(controls);
+                controls.clone()
+            }
+            DemoState::Idle => controls.clone(),
+        };
+        self.update(&controls);
+        self.audio.tick();
+    }
+}
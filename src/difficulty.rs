@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+/// Skill level picked when starting a new game, named like the original's.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Difficulty {
+    Baby,
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Baby,
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Baby => "Can I play, Daddy?",
+            Difficulty::Easy => "Don't hurt me.",
+            Difficulty::Medium => "Bring 'em on!",
+            Difficulty::Hard => "I am Death incarnate!",
+        }
+    }
+
+    /// Highest guard tier placed, see `guard_tier`. Both easy skills share the first tier.
+    pub fn guard_tier(&self) -> u8 {
+        match self {
+            Difficulty::Baby | Difficulty::Easy => 0,
+            Difficulty::Medium => 1,
+            Difficulty::Hard => 2,
+        }
+    }
+
+    /// Damage actually taken from a hit of `amount`.
+    pub fn damage(&self, amount: u32) -> u32 {
+        match self {
+            // The original only went easy on the lowest skill, with a quarter of the damage
+            Difficulty::Baby => amount / 4,
+            Difficulty::Easy => amount / 2,
+            Difficulty::Medium | Difficulty::Hard => amount,
+        }
+    }
+
+    /// How long enemies take to react, scaled from the medium skill's `ticks`.
+    pub fn reaction_ticks(&self, ticks: u32) -> u32 {
+        match self {
+            Difficulty::Baby => ticks * 2,
+            Difficulty::Easy => ticks * 3 / 2,
+            Difficulty::Medium => ticks,
+            Difficulty::Hard => ticks / 2,
+        }
+    }
+}
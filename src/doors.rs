@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+
+use crate::map::*;
+
+/// Fraction of a tile a door slides per tick.
+const DOOR_SPEED: f32 = 1.0 / 30.0;
+/// Ticks a door stays open before trying to close again.
+const DOOR_OPEN_TICKS: u32 = 300;
+const RATTLE_TICKS: u32 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Lock {
+    None,
+    Gold,
+    Silver,
+}
+
+impl Lock {
+    pub fn from_tile(tile: u8) -> Option<Self> {
+        match tile {
+            DOOR => Some(Lock::None),
+            GOLD_DOOR => Some(Lock::Gold),
+            SILVER_DOOR => Some(Lock::Silver),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lock::None => "",
+            Lock::Gold => "gold",
+            Lock::Silver => "silver",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DoorState {
+    Closed,
+    Opening,
+    /// Fully open, with the number of ticks left before it closes.
+    Open(u32),
+    Closing,
+}
+
+/// A sliding door occupying a single map cell.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Door {
+    pub x: usize,
+    pub y: usize,
+    pub lock: Lock,
+    /// How far the door has slid into the wall, from 0 (closed) to 1 (open).
+    pub open: f32,
+    pub state: DoorState,
+    /// Ticks left of the "locked" shake.
+    pub rattle: u32,
+}
+
+impl Door {
+    pub fn new(x: usize, y: usize, lock: Lock) -> Self {
+        Self {
+            x,
+            y,
+            lock,
+            open: 0.0,
+            state: DoorState::Closed,
+            rattle: 0,
+        }
+    }
+
+    pub fn is_passable(&self) -> bool {
+        self.open >= 1.0
+    }
+
+    pub fn toggle(&mut self) {
+        self.state = match self.state {
+            DoorState::Closed | DoorState::Closing => DoorState::Opening,
+            DoorState::Open(_) | DoorState::Opening => DoorState::Closing,
+        };
+    }
+
+    pub fn rattle(&mut self) {
+        self.rattle = RATTLE_TICKS;
+    }
+
+    /// Advance the door by one tick. A `blocked` door won't start closing.
+    pub fn update(&mut self, blocked: bool) {
+        self.rattle = self.rattle.saturating_sub(1);
+        match self.state {
+            DoorState::Closed => {}
+            DoorState::Opening => {
+                self.open = (self.open + DOOR_SPEED).min(1.0);
+                if self.open >= 1.0 {
+                    self.state = DoorState::Open(DOOR_OPEN_TICKS);
+                }
+            }
+            DoorState::Open(0) => {
+                if !blocked {
+                    self.state = DoorState::Closing;
+                }
+            }
+            DoorState::Open(ticks) => self.state = DoorState::Open(ticks - 1),
+            DoorState::Closing => {
+                if blocked {
+                    self.state = DoorState::Opening;
+                    return;
+                }
+                self.open = (self.open - DOOR_SPEED).max(0.0);
+                if self.open <= 0.0 {
+                    self.state = DoorState::Closed;
+                }
+            }
+        }
+    }
+
+    /// Horizontal shift of the door face, in fractions of a tile.
+    pub fn offset(&self) -> f32 {
+        let shake = if self.rattle > 0 {
+            if self.rattle % 4 < 2 {
+                0.03
+            } else {
+                -0.03
+            }
+        } else {
+            0.0
+        };
+        self.open + shake
+    }
+}
+
+pub fn find_doors(map: &Map) -> Vec<Door> {
+    let mut doors = vec![];
+    for y in 0..map.height {
+        for x in 0..map.width {
+            if let Some(lock) = Lock::from_tile(map.tile(x, y)) {
+                doors.push(Door::new(x, y, lock));
+            }
+        }
+    }
+    doors
+}
@@ -0,0 +1,433 @@
+use std::fs;
+
+use egui::mutex::Mutex;
+use egui::{vec2, Color32, DragValue, Key, Modifiers, Pos2, Rect, Sense, Stroke, Ui};
+use std::sync::Arc;
+
+use wolfenstein_rs::{levels::LEVELS, map::*, World};
+
+/// Size of a map cell in the editor grid, in points.
+const CELL_SIZE: f32 = 12.0;
+const MIN_SIZE: usize = 4;
+/// Oldest snapshots are dropped once there are more than this many.
+const MAX_UNDO: usize = 100;
+
+const FLOOR_COLOR: Color32 = Color32::from_rgb(24, 24, 24);
+const GRID_COLOR: Color32 = Color32::from_rgb(48, 48, 48);
+const DOOR_COLOR: Color32 = Color32::from_rgb(100, 180, 255);
+const GOLD_COLOR: Color32 = Color32::from_rgb(255, 205, 25);
+const SILVER_COLOR: Color32 = Color32::from_rgb(190, 205, 215);
+const ELEVATOR_COLOR: Color32 = Color32::from_rgb(205, 155, 75);
+const START_COLOR: Color32 = Color32::WHITE;
+const ITEM_COLOR: Color32 = Color32::from_rgb(50, 205, 50);
+const WEAPON_COLOR: Color32 = Color32::from_rgb(50, 205, 205);
+const GUARD_COLOR: Color32 = Color32::from_rgb(255, 50, 50);
+const PUSHWALL_COLOR: Color32 = Color32::from_rgb(205, 50, 205);
+const PLAYER_COLOR: Color32 = Color32::from_rgb(255, 120, 0);
+
+/// What clicking a cell paints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Brush {
+    /// Value for the wall plane, 0 erases.
+    Tile(u8),
+    /// Value for the object plane, 0 erases.
+    Object(u8),
+}
+
+impl Brush {
+    fn apply(self, map: &mut Map, x: usize, y: usize) {
+        match self {
+            Brush::Tile(tile) => map.set_tile(x, y, tile),
+            Brush::Object(object) => {
+                // There can only be one player start
+                if is_player_start(object) {
+                    for start in map.objects.iter_mut().filter(|o| is_player_start(**o)) {
+                        *start = 0;
+                    }
+                }
+                map.set_object(x, y, object);
+            }
+        }
+    }
+}
+
+fn is_player_start(object: u8) -> bool {
+    (PLAYER_START_NORTH..=PLAYER_START_WEST).contains(&object)
+}
+
+/// Every brush with its label, walls first.
+fn palette(textures: usize, masked: usize) -> Vec<(String, Brush)> {
+    let mut brushes: Vec<(String, Brush)> = (1..=textures.min(WALL_TEXTURES as usize) as u8)
+        .map(|tile| (format!("Wall {tile}"), Brush::Tile(tile)))
+        .chain(
+            (FIRST_MASKED_WALL..FIRST_MASKED_WALL + masked as u8)
+                .take_while(|&tile| is_masked_wall(tile))
+                .map(|tile| (format!("See-through wall {tile}"), Brush::Tile(tile))),
+        )
+        .collect();
+    let named = [
+        ("Door", Brush::Tile(DOOR)),
+        ("Gold door", Brush::Tile(GOLD_DOOR)),
+        ("Silver door", Brush::Tile(SILVER_DOOR)),
+        ("Elevator", Brush::Tile(ELEVATOR_SWITCH)),
+        ("Secret elevator", Brush::Tile(SECRET_ELEVATOR_SWITCH)),
+        ("Erase wall", Brush::Tile(0)),
+        ("Start north", Brush::Object(PLAYER_START_NORTH)),
+        ("Start east", Brush::Object(PLAYER_START_EAST)),
+        ("Start south", Brush::Object(PLAYER_START_SOUTH)),
+        ("Start west", Brush::Object(PLAYER_START_WEST)),
+        ("Guard east", Brush::Object(GUARD_EAST)),
+        ("Guard north", Brush::Object(GUARD_NORTH)),
+        ("Guard west", Brush::Object(GUARD_WEST)),
+        ("Guard south", Brush::Object(GUARD_SOUTH)),
+        (
+            "Guard (medium+)",
+            Brush::Object(GUARD_SOUTH + GUARD_TIER_OFFSET),
+        ),
+        (
+            "Guard (hard)",
+            Brush::Object(GUARD_SOUTH + 2 * GUARD_TIER_OFFSET),
+        ),
+        ("Gold key", Brush::Object(GOLD_KEY)),
+        ("Silver key", Brush::Object(SILVER_KEY)),
+        ("First aid", Brush::Object(FIRST_AID)),
+        ("Ammo clip", Brush::Object(AMMO_CLIP)),
+        ("Machine gun", Brush::Object(MACHINE_GUN)),
+        ("Chain gun", Brush::Object(CHAIN_GUN)),
+        ("Cross", Brush::Object(CROSS)),
+        ("Chalice", Brush::Object(CHALICE)),
+        ("Chest", Brush::Object(CHEST)),
+        ("Crown", Brush::Object(CROWN)),
+        ("Push wall", Brush::Object(PUSHWALL)),
+        ("Erase object", Brush::Object(0)),
+    ];
+    brushes.extend(
+        named
+            .into_iter()
+            .map(|(label, brush)| (label.to_string(), brush)),
+    );
+    brushes
+}
+
+/// Editor for the current level's map. Every change is applied to the world right away.
+pub struct Editor {
+    pub open: bool,
+    brush: Brush,
+    /// Maps before each change, most recent last.
+    undo: Vec<Map>,
+    redo: Vec<Map>,
+    /// Whether the current mouse stroke already pushed an undo snapshot.
+    stroke_changed: bool,
+    width: usize,
+    height: usize,
+    /// Map file opened and saved by the editor.
+    path: String,
+    /// Outcome of the last open or save.
+    status: String,
+    /// Average colour of each wall texture, filled in on first use.
+    wall_colors: Vec<Color32>,
+    /// Average colour of each see-through wall texture, filled in on first use.
+    masked_colors: Vec<Color32>,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self {
+            open: false,
+            brush: Brush::Tile(1),
+            undo: vec![],
+            redo: vec![],
+            stroke_changed: false,
+            width: MAP_WIDTH,
+            height: MAP_HEIGHT,
+            path: "assets/maps/custom.map".to_string(),
+            status: String::new(),
+            wall_colors: vec![],
+            masked_colors: vec![],
+        }
+    }
+}
+
+impl Editor {
+    pub fn ui(&mut self, ctx: &egui::Context, world: &Arc<Mutex<World>>) {
+        if !self.open {
+            return;
+        }
+        let mut open = self.open;
+        egui::Window::new("Map editor")
+            .open(&mut open)
+            .show(ctx, |ui| self.contents(ui, &mut world.lock()));
+        self.open = open;
+    }
+
+    fn contents(&mut self, ui: &mut Ui, world: &mut World) {
+        if self.wall_colors.is_empty() {
+            self.wall_colors = world
+                .assets
+                .textures
+                .iter()
+                .map(|texture| average_color(texture))
+                .collect();
+            self.masked_colors = world
+                .assets
+                .masked
+                .iter()
+                .map(|texture| average_color(texture))
+                .collect();
+        }
+        let mut map = world.map.clone();
+
+        let (undo, redo) = ui.input_mut(|input| {
+            (
+                input.consume_key(Modifiers::COMMAND, Key::Z),
+                input.consume_key(Modifiers::COMMAND, Key::Y),
+            )
+        });
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!self.undo.is_empty(), egui::Button::new("Undo"))
+                .clicked()
+                || undo
+            {
+                if let Some(previous) = self.undo.pop() {
+                    self.redo.push(std::mem::replace(&mut map, previous));
+                }
+            }
+            if ui
+                .add_enabled(!self.redo.is_empty(), egui::Button::new("Redo"))
+                .clicked()
+                || redo
+            {
+                if let Some(next) = self.redo.pop() {
+                    self.undo.push(std::mem::replace(&mut map, next));
+                }
+            }
+            if ui.button("Revert").clicked() {
+                let original = LEVELS[world.level_index].map();
+                self.push_undo(std::mem::replace(&mut map, original));
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Name:");
+            ui.text_edit_singleline(&mut map.name);
+            ui.label("Par:");
+            ui.add(DragValue::new(&mut map.par).suffix(" s"));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Size:");
+            let range = MIN_SIZE..=MAX_MAP_SIZE;
+            ui.add(DragValue::new(&mut self.width).clamp_range(range.clone()));
+            ui.label("x");
+            ui.add(DragValue::new(&mut self.height).clamp_range(range));
+            if ui.button("Resize").clicked() {
+                let resized = map.resized(self.width, self.height);
+                self.push_undo(std::mem::replace(&mut map, resized));
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("File:");
+            ui.text_edit_singleline(&mut self.path);
+            if ui.button("Open").clicked() {
+                match fs::read_to_string(&self.path) {
+                    Ok(source) => match Map::parse(&source) {
+                        Ok(opened) => {
+                            self.push_undo(std::mem::replace(&mut map, opened));
+                            self.status = format!("Opened {}", self.path);
+                        }
+                        Err(err) => self.status = format!("Open failed: {err}"),
+                    },
+                    Err(err) => self.status = format!("Open failed: {err}"),
+                }
+            }
+            if ui.button("Save").clicked() {
+                self.status = match fs::write(&self.path, map.to_string()) {
+                    Ok(()) => format!("Saved {}", self.path),
+                    Err(err) => format!("Save failed: {err}"),
+                };
+            }
+        });
+        if !self.status.is_empty() {
+            ui.label(&self.status);
+        }
+        ui.separator();
+
+        ui.horizontal_top(|ui| {
+            egui::ScrollArea::vertical()
+                .id_source("palette")
+                .max_height(400.0)
+                .show(ui, |ui| {
+                    for (label, brush) in palette(self.wall_colors.len(), self.masked_colors.len())
+                    {
+                        ui.selectable_value(&mut self.brush, brush, label);
+                    }
+                });
+            egui::ScrollArea::both()
+                .id_source("grid")
+                .max_height(400.0)
+                .show(ui, |ui| self.grid(ui, &mut map, world));
+        });
+
+        if map.walls != world.map.walls || map.objects != world.map.objects {
+            // Size fields follow the map unless the user is about to resize it
+            if map.width != world.map.width || map.height != world.map.height {
+                self.width = map.width;
+                self.height = map.height;
+            }
+            if !world.refuse_while_recording("edit the map") {
+                world.set_map(map);
+            }
+        } else {
+            // Name and par don't need the level to be respawned
+            world.map = map;
+        }
+    }
+
+    /// Draw the map and paint the cells under the mouse with the current brush.
+    fn grid(&mut self, ui: &mut Ui, map: &mut Map, world: &World) {
+        let size = vec2(map.width as f32, map.height as f32) * CELL_SIZE;
+        let (response, painter) = ui.allocate_painter(size, Sense::click_and_drag());
+        let origin = response.rect.min;
+        let cell_rect = |x: usize, y: usize| {
+            Rect::from_min_size(
+                origin + vec2(x as f32, y as f32) * CELL_SIZE,
+                vec2(CELL_SIZE, CELL_SIZE),
+            )
+        };
+        let (width, height) = (map.width, map.height);
+        let cell_at = |pos: Pos2| {
+            let offset = (pos - origin) / CELL_SIZE;
+            let (x, y) = (offset.x.floor(), offset.y.floor());
+            (x >= 0.0 && y >= 0.0 && (x as usize) < width && (y as usize) < height)
+                .then_some((x as usize, y as usize))
+        };
+
+        if response.is_pointer_button_down_on() {
+            if let Some((x, y)) = response.interact_pointer_pos().and_then(cell_at) {
+                let before = map.clone();
+                self.brush.apply(map, x, y);
+                if *map != before && !self.stroke_changed {
+                    self.push_undo(before);
+                    self.stroke_changed = true;
+                }
+            }
+        } else {
+            self.stroke_changed = false;
+        }
+        let hovered = response.hover_pos().and_then(cell_at);
+
+        painter.rect_filled(response.rect, 0.0, FLOOR_COLOR);
+        for y in 0..map.height {
+            for x in 0..map.width {
+                let rect = cell_rect(x, y);
+                let tile = map.tile(x, y);
+                let color = match tile {
+                    0 => None,
+                    DOOR => Some(DOOR_COLOR),
+                    GOLD_DOOR => Some(GOLD_COLOR),
+                    SILVER_DOOR => Some(SILVER_COLOR),
+                    ELEVATOR_SWITCH | SECRET_ELEVATOR_SWITCH => Some(ELEVATOR_COLOR),
+                    tile if is_masked_wall(tile) => self
+                        .masked_colors
+                        .get((tile - FIRST_MASKED_WALL) as usize)
+                        .copied(),
+                    _ => self.wall_colors.get(tile as usize - 1).copied(),
+                };
+                match color {
+                    // Doors are drawn thinner so they stand out from the walls
+                    Some(color) if is_door(tile) => {
+                        painter.rect_filled(rect.shrink(CELL_SIZE / 4.0), 0.0, color)
+                    }
+                    // See-through walls only have their outline drawn
+                    Some(color) if is_masked_wall(tile) => {
+                        painter.rect_stroke(rect.shrink(1.5), 0.0, Stroke::new(3.0, color))
+                    }
+                    Some(color) => painter.rect_filled(rect, 0.0, color),
+                    None => painter.rect_stroke(rect, 0.0, Stroke::new(0.5, GRID_COLOR)),
+                }
+                draw_object(&painter, rect, map.object(x, y));
+            }
+        }
+        if let Some((x, y)) = map.cell_at(world.x, world.y) {
+            let center = cell_rect(x, y).center();
+            painter.circle_filled(center, CELL_SIZE / 3.0, PLAYER_COLOR);
+            let forward = vec2(world.theta.cos(), world.theta.sin());
+            painter.line_segment(
+                [center, center + forward * CELL_SIZE],
+                Stroke::new(2.0, PLAYER_COLOR),
+            );
+        }
+        if let Some((x, y)) = hovered {
+            painter.rect_stroke(cell_rect(x, y), 0.0, Stroke::new(1.0, Color32::WHITE));
+            response.on_hover_text_at_pointer(format!(
+                "{x}, {y}: wall {} object {}",
+                map.tile(x, y),
+                map.object(x, y)
+            ));
+        }
+    }
+
+    /// Remember `map` so the change replacing it can be undone.
+    fn push_undo(&mut self, map: Map) {
+        self.undo.push(map);
+        if self.undo.len() > MAX_UNDO {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+}
+
+fn draw_object(painter: &egui::Painter, rect: Rect, object: u8) {
+    // Guards for harder skills are drawn like the ones placed on every skill
+    let object = match guard_tier(object) {
+        Some(tier) => object - tier * GUARD_TIER_OFFSET,
+        None => object,
+    };
+    let center = rect.center();
+    let radius = CELL_SIZE / 4.0;
+    let direction = match object {
+        PLAYER_START_NORTH | GUARD_NORTH => vec2(0.0, -1.0),
+        PLAYER_START_EAST | GUARD_EAST => vec2(1.0, 0.0),
+        PLAYER_START_SOUTH | GUARD_SOUTH => vec2(0.0, 1.0),
+        PLAYER_START_WEST | GUARD_WEST => vec2(-1.0, 0.0),
+        _ => vec2(0.0, 0.0),
+    };
+    let color = match object {
+        0 => return,
+        PLAYER_START_NORTH..=PLAYER_START_WEST => START_COLOR,
+        GUARD_EAST..=GUARD_SOUTH => GUARD_COLOR,
+        GOLD_KEY => GOLD_COLOR,
+        SILVER_KEY => SILVER_COLOR,
+        MACHINE_GUN | CHAIN_GUN => WEAPON_COLOR,
+        CROSS..=CROWN => GOLD_COLOR,
+        PUSHWALL => {
+            painter.rect_stroke(rect.shrink(1.0), 0.0, Stroke::new(2.0, PUSHWALL_COLOR));
+            return;
+        }
+        _ => ITEM_COLOR,
+    };
+    painter.circle_filled(center, radius, color);
+    if direction != vec2(0.0, 0.0) {
+        painter.line_segment(
+            [center, center + direction * CELL_SIZE / 2.0],
+            Stroke::new(1.5, color),
+        );
+    }
+}
+
+/// Average colour of a texture stored as columns of RGBA pixels.
+fn average_color(texture: &[Vec<u8>]) -> Color32 {
+    let mut sum = [0u64; 3];
+    let mut count = 0u64;
+    for pixel in texture
+        .iter()
+        .flat_map(|column| column.chunks_exact(4))
+        .filter(|pixel| pixel[3] > 0)
+    {
+        for (total, &channel) in sum.iter_mut().zip(pixel) {
+            *total += channel as u64;
+        }
+        count += 1;
+    }
+    let [r, g, b] = sum.map(|total| total.checked_div(count).unwrap_or(0) as u8);
+    Color32::from_rgb(r, g, b)
+}
@@ -0,0 +1,38 @@
+use crate::World;
+
+impl World {
+    /// Mark cells as seen, `cells` holding one flag per cell like `seen` does.
+    pub fn reveal(&mut self, cells: &[bool]) {
+        for (seen, &revealed) in self.seen.iter_mut().zip(cells) {
+            *seen |= revealed;
+        }
+    }
+
+    pub fn is_seen(&self, x: usize, y: usize) -> bool {
+        self.seen[x + y * self.map.width]
+    }
+
+    /// Whether the automap shows the cell, which is every cell with the reveal map cheat.
+    pub fn is_mapped(&self, x: usize, y: usize) -> bool {
+        self.cheats.reveal_map || self.is_seen(x, y)
+    }
+
+    /// Share of the cells the player can stand in (floor and doors) seen so far, in percent.
+    pub fn explored_percent(&self) -> u32 {
+        let mut open = 0u32;
+        let mut seen = 0u32;
+        for y in 0..self.map.height {
+            for x in 0..self.map.width {
+                let tile = self.map.tile(x, y);
+                if tile != 0 && self.door_at(x, y).is_none() {
+                    continue;
+                }
+                open += 1;
+                if self.is_seen(x, y) {
+                    seen += 1;
+                }
+            }
+        }
+        (seen * 100).checked_div(open).unwrap_or(0)
+    }
+}
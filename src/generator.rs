@@ -0,0 +1,458 @@
+use std::collections::VecDeque;
+
+use crate::{hud::Message, map::*, rng::Rng, World};
+
+pub const MIN_SIZE: usize = 16;
+pub const MAX_SIZE: usize = 64;
+const MIN_ROOM: usize = 3;
+const MAX_ROOM: usize = 8;
+/// Cells of map area per room aimed for.
+const AREA_PER_ROOM: usize = 48;
+/// Room positions tried per layout before giving up on adding more.
+const ROOM_TRIES: usize = 200;
+/// Layouts tried before giving up on a seed.
+const MAX_ATTEMPTS: usize = 20;
+
+const STEPS: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// Settings for `generate`, the same seed and size always giving the same map.
+#[derive(Debug, Clone, Copy)]
+pub struct GeneratorOptions {
+    pub seed: u32,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        Self {
+            seed: 1,
+            width: 32,
+            height: 32,
+        }
+    }
+}
+
+/// Floor area of a room, walls not included.
+#[derive(Debug, Clone, Copy)]
+struct Room {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Room {
+    fn center(&self) -> (usize, usize) {
+        (self.x + self.width / 2, self.y + self.height / 2)
+    }
+
+    /// Whether the rooms overlap or would share a wall.
+    fn touches(&self, other: &Room) -> bool {
+        self.x < other.x + other.width + 1
+            && other.x < self.x + self.width + 1
+            && self.y < other.y + other.height + 1
+            && other.y < self.y + self.height + 1
+    }
+
+    fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+
+    fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (self.y..self.y + self.height)
+            .flat_map(move |y| (self.x..self.x + self.width).map(move |x| (x, y)))
+    }
+
+    /// Wall cells next to the floor, without the corners, with the direction facing away from the room.
+    fn walls(&self) -> Vec<(usize, usize, isize, isize)> {
+        let mut walls = vec![];
+        for x in self.x..self.x + self.width {
+            walls.push((x, self.y - 1, 0, -1));
+            walls.push((x, self.y + self.height, 0, 1));
+        }
+        for y in self.y..self.y + self.height {
+            walls.push((self.x - 1, y, -1, 0));
+            walls.push((self.x + self.width, y, 1, 0));
+        }
+        walls
+    }
+}
+
+/// Random value from `min` to `max`, both included.
+fn range(rng: &mut Rng, min: usize, max: usize) -> usize {
+    let value = (rng.rnd() << 8 | rng.rnd()) as usize;
+    min + value % (max - min + 1)
+}
+
+fn offset(map: &Map, x: usize, y: usize, dx: isize, dy: isize) -> Option<(usize, usize)> {
+    let x = x.checked_add_signed(dx)?;
+    let y = y.checked_add_signed(dy)?;
+    (x < map.width && y < map.height).then_some((x, y))
+}
+
+fn is_open(map: &Map, x: usize, y: usize) -> bool {
+    let tile = map.tile(x, y);
+    tile == 0 || is_door(tile)
+}
+
+/// Number of steps from `x`, `y` to every cell, walking through floor and doors.
+/// `None` for cells that can't be reached.
+pub fn distances(map: &Map, x: usize, y: usize) -> Vec<Option<usize>> {
+    let mut distances = vec![None; map.width * map.height];
+    distances[x + y * map.width] = Some(0);
+    let mut queue = VecDeque::from([(x, y, 0)]);
+    while let Some((x, y, distance)) = queue.pop_front() {
+        for (dx, dy) in STEPS {
+            let Some((x, y)) = offset(map, x, y, dx, dy) else {
+                continue;
+            };
+            let cell = &mut distances[x + y * map.width];
+            if cell.is_none() && is_open(map, x, y) {
+                *cell = Some(distance + 1);
+                queue.push_back((x, y, distance + 1));
+            }
+        }
+    }
+    distances
+}
+
+/// Generate a level of rooms joined by corridors and doors, with guards, pickups,
+/// a push wall secret and an elevator that can be reached from the player start.
+/// Returns `None` if no such layout turned up, which only happens on very cramped maps.
+pub fn generate(options: &GeneratorOptions) -> Option<Map> {
+    let width = options.width.clamp(MIN_SIZE, MAX_SIZE);
+    let height = options.height.clamp(MIN_SIZE, MAX_SIZE);
+    let mut rng = Rng::seeded(options.seed);
+    let mut map = (0..MAX_ATTEMPTS).find_map(|_| try_generate(&mut rng, width, height))?;
+    map.name = format!("Random {}", options.seed);
+    Some(map)
+}
+
+fn try_generate(rng: &mut Rng, width: usize, height: usize) -> Option<Map> {
+    let mut map = Map::new(width, height);
+    map.walls.fill(range(rng, 1, WALL_TEXTURES as usize) as u8);
+
+    let max_rooms = (width * height / AREA_PER_ROOM).max(2);
+    let mut rooms: Vec<Room> = vec![];
+    for _ in 0..ROOM_TRIES {
+        if rooms.len() == max_rooms {
+            break;
+        }
+        let room_width = range(rng, MIN_ROOM, MAX_ROOM);
+        let room_height = range(rng, MIN_ROOM, MAX_ROOM);
+        let room = Room {
+            x: range(rng, 1, width - 1 - room_width),
+            y: range(rng, 1, height - 1 - room_height),
+            width: room_width,
+            height: room_height,
+        };
+        if rooms.iter().all(|other| !room.touches(other)) {
+            rooms.push(room);
+        }
+    }
+    if rooms.len() < 2 {
+        return None;
+    }
+
+    for room in &rooms {
+        let texture = range(rng, 1, WALL_TEXTURES as usize) as u8;
+        for (x, y, _, _) in room.walls() {
+            map.set_tile(x, y, texture);
+        }
+        for (x, y) in room.cells() {
+            map.set_tile(x, y, 0);
+        }
+    }
+
+    // Join every room to the closest one placed before it, which connects them all
+    let mut corridors = vec![false; width * height];
+    for (index, room) in rooms.iter().enumerate().skip(1) {
+        let (x, y) = room.center();
+        let (target_x, target_y) = rooms[..index]
+            .iter()
+            .map(Room::center)
+            .min_by_key(|&(other_x, other_y)| x.abs_diff(other_x) + y.abs_diff(other_y))?;
+        let horizontal_first = rng.rnd() < 128;
+        let corner = if horizontal_first {
+            (target_x, y)
+        } else {
+            (x, target_y)
+        };
+        for ((from_x, from_y), (to_x, to_y)) in [((x, y), corner), (corner, (target_x, target_y))] {
+            for cell_y in from_y.min(to_y)..=from_y.max(to_y) {
+                for cell_x in from_x.min(to_x)..=from_x.max(to_x) {
+                    if map.tile(cell_x, cell_y) != 0 {
+                        map.set_tile(cell_x, cell_y, 0);
+                        corridors[cell_x + cell_y * width] = true;
+                    }
+                }
+            }
+        }
+    }
+
+    // Doors where corridors break through a room's wall
+    let in_room = |x: usize, y: usize| rooms.iter().any(|room| room.contains(x, y));
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            if !corridors[x + y * width] {
+                continue;
+            }
+            let solid = |x: usize, y: usize| !is_open(&map, x, y);
+            let across_x =
+                solid(x, y - 1) && solid(x, y + 1) && !solid(x - 1, y) && !solid(x + 1, y);
+            let across_y =
+                solid(x - 1, y) && solid(x + 1, y) && !solid(x, y - 1) && !solid(x, y + 1);
+            let enters_room =
+                in_room(x - 1, y) || in_room(x + 1, y) || in_room(x, y - 1) || in_room(x, y + 1);
+            let next_to_door = STEPS.iter().any(|&(dx, dy)| {
+                offset(&map, x, y, dx, dy).is_some_and(|(x, y)| is_door(map.tile(x, y)))
+            });
+            if (across_x || across_y) && enters_room && !next_to_door && rng.rnd() < 192 {
+                map.set_tile(x, y, DOOR);
+            }
+        }
+    }
+
+    let (start_x, start_y) = rooms[0].center();
+    map.set_object(
+        start_x,
+        start_y,
+        PLAYER_START_NORTH + range(rng, 0, 3) as u8,
+    );
+    let distances = distances(&map, start_x, start_y);
+    let distance = |x: usize, y: usize| distances[x + y * width];
+    if rooms.iter().any(|room| {
+        let (x, y) = room.center();
+        distance(x, y).is_none()
+    }) {
+        return None;
+    }
+
+    // The elevator goes in the room furthest from the start
+    let (exit_room, exit_distance) = rooms
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter_map(|(index, room)| {
+            let (x, y) = room.center();
+            Some((index, distance(x, y)?))
+        })
+        .max_by_key(|&(_, distance)| distance)?;
+    let switches: Vec<_> = rooms[exit_room]
+        .walls()
+        .into_iter()
+        .filter(|&(x, y, _, _)| !is_open(&map, x, y))
+        .collect();
+    let &(x, y, _, _) = switches.get(range(rng, 0, switches.len().max(1) - 1))?;
+    map.set_tile(x, y, ELEVATOR_SWITCH);
+    map.par = (exit_distance as u32 / 2 + 30).next_multiple_of(15);
+
+    place_secret(rng, &mut map, &rooms, &corridors);
+
+    // Fill the rooms, leaving the first one quiet
+    let gun_room = range(rng, 1, rooms.len() - 1);
+    for (index, room) in rooms.iter().enumerate() {
+        let mut objects = vec![];
+        if index == 0 {
+            objects.push(AMMO_CLIP);
+        } else {
+            let area = room.width * room.height;
+            for _ in 0..range(rng, 0, 1 + area / 16) {
+                // Later guards only show up on harder skills
+                let tier = range(rng, 0, 2) as u8;
+                objects.push(GUARD_EAST + range(rng, 0, 3) as u8 + tier * GUARD_TIER_OFFSET);
+            }
+            if rng.rnd() < 128 {
+                objects.push(AMMO_CLIP);
+            }
+            if rng.rnd() < 80 {
+                objects.push(FIRST_AID);
+            }
+            for _ in 0..range(rng, 0, 1) {
+                objects.push(CROSS + range(rng, 0, 3) as u8);
+            }
+            if index == gun_room {
+                objects.push(MACHINE_GUN);
+            }
+        }
+        for object in objects {
+            let free: Vec<_> = room
+                .cells()
+                .filter(|&(x, y)| map.object(x, y) == 0)
+                .collect();
+            if free.is_empty() {
+                break;
+            }
+            let (x, y) = free[range(rng, 0, free.len() - 1)];
+            map.set_object(x, y, object);
+        }
+    }
+    Some(map)
+}
+
+/// Turn a room wall with solid rock behind it into a push wall hiding some treasure.
+fn place_secret(rng: &mut Rng, map: &mut Map, rooms: &[Room], corridors: &[bool]) {
+    let first = range(rng, 0, rooms.len() - 1);
+    for room in rooms[first..].iter().chain(&rooms[..first]) {
+        for (x, y, dx, dy) in room.walls() {
+            if !(1..=WALL_TEXTURES).contains(&map.tile(x, y)) {
+                continue;
+            }
+            // The wall slides two cells into a closet which has to be surrounded by rock
+            let closet = [1, 2].map(|step| offset(map, x, y, dx * step, dy * step));
+            let [Some(first), Some(second)] = closet else {
+                continue;
+            };
+            let rock = [first, second].iter().all(|&(cell_x, cell_y)| {
+                let (ahead, side) = ((dx, dy), (dy, dx));
+                [ahead, side, (-side.0, -side.1)]
+                    .into_iter()
+                    .chain([(0, 0)])
+                    .all(|(step_x, step_y)| {
+                        offset(map, cell_x, cell_y, step_x, step_y).is_some_and(|(x, y)| {
+                            x > 0
+                                && y > 0
+                                && x < map.width - 1
+                                && y < map.height - 1
+                                && !is_open(map, x, y)
+                                && !corridors[x + y * map.width]
+                        })
+                    })
+            });
+            if !rock {
+                continue;
+            }
+            for (cell_x, cell_y) in [first, second] {
+                map.set_tile(cell_x, cell_y, 0);
+            }
+            map.set_object(x, y, PUSHWALL);
+            map.set_object(first.0, first.1, CHEST + range(rng, 0, 1) as u8);
+            return;
+        }
+    }
+}
+
+impl World {
+    /// Replace the current level with a generated one.
+    pub fn random_level(&mut self, options: &GeneratorOptions) {
+        if self.refuse_while_recording("change levels") {
+            return;
+        }
+        match generate(options) {
+            Some(map) => self.start_map(map),
+            None => {
+                self.message = Some(Message::new(format!(
+                    "No level fits seed {} at {}x{}",
+                    options.seed, options.width, options.height
+                )))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZES: [(usize, usize); 6] = [
+        (MIN_SIZE, MIN_SIZE),
+        (24, 24),
+        (32, 32),
+        (48, 20),
+        (20, 48),
+        (MAX_SIZE, MAX_SIZE),
+    ];
+
+    fn maps() -> impl Iterator<Item = (GeneratorOptions, Option<Map>)> {
+        SIZES.into_iter().flat_map(|(width, height)| {
+            (0..100).map(move |seed| {
+                let options = GeneratorOptions {
+                    seed,
+                    width,
+                    height,
+                };
+                (options, generate(&options))
+            })
+        })
+    }
+
+    fn start(map: &Map) -> (usize, usize) {
+        (0..map.height)
+            .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+            .find(|&(x, y)| (PLAYER_START_NORTH..=PLAYER_START_WEST).contains(&map.object(x, y)))
+            .expect("map has a player start")
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_map() {
+        let options = GeneratorOptions::default();
+        assert_eq!(generate(&options), generate(&options));
+    }
+
+    #[test]
+    fn only_cramped_maps_fail() {
+        for (options, map) in maps() {
+            assert!(
+                map.is_some() || options.width.min(options.height) == MIN_SIZE,
+                "{options:?} gave no map"
+            );
+        }
+    }
+
+    #[test]
+    fn every_room_and_the_elevator_can_be_reached() {
+        for (options, map) in maps() {
+            let Some(mut map) = map else {
+                continue;
+            };
+            // The secret closet opens up once its push wall is moved
+            for y in 0..map.height {
+                for x in 0..map.width {
+                    if map.object(x, y) == PUSHWALL {
+                        map.set_tile(x, y, 0);
+                    }
+                }
+            }
+            let (start_x, start_y) = start(&map);
+            let distances = distances(&map, start_x, start_y);
+            let mut elevators = 0;
+            for y in 0..map.height {
+                for x in 0..map.width {
+                    if is_open(&map, x, y) {
+                        assert!(
+                            distances[x + y * map.width].is_some(),
+                            "{options:?}: {x}, {y} can't be reached"
+                        );
+                    }
+                    if map.tile(x, y) == ELEVATOR_SWITCH {
+                        elevators += 1;
+                        let reachable = STEPS.iter().any(|&(dx, dy)| {
+                            offset(&map, x, y, dx, dy)
+                                .is_some_and(|(x, y)| distances[x + y * map.width].is_some())
+                        });
+                        assert!(reachable, "{options:?}: elevator can't be reached");
+                    }
+                }
+            }
+            assert_eq!(elevators, 1, "{options:?}");
+        }
+    }
+
+    #[test]
+    fn maps_only_use_known_codes() {
+        for (options, map) in maps() {
+            let Some(map) = map else {
+                continue;
+            };
+            assert!(
+                map.walls.iter().all(|&tile| is_known_wall(tile)),
+                "{options:?}"
+            );
+            assert!(
+                map.objects.iter().all(|&object| is_known_object(object)),
+                "{options:?}"
+            );
+        }
+    }
+}
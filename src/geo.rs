@@ -48,6 +48,36 @@ impl Rect {
         Vec2::new(self.x + self.width / 2.0, self.y + self.height / 2.0)
     }
 
+    /// Part of the segment from `start` to `end` inside the rectangle (Liang-Barsky).
+    pub fn clip_line(&self, start: Vec2, end: Vec2) -> Option<(Vec2, Vec2)> {
+        let delta = end - start;
+        let mut t0: f32 = 0.0;
+        let mut t1: f32 = 1.0;
+        for (p, q) in [
+            (-delta.x, start.x - self.x),
+            (delta.x, self.x + self.width - start.x),
+            (-delta.y, start.y - self.y),
+            (delta.y, self.y + self.height - start.y),
+        ] {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return None;
+                }
+                continue;
+            }
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+            if t0 > t1 {
+                return None;
+            }
+        }
+        Some((start + delta * t0, start + delta * t1))
+    }
+
     pub fn points(&self) -> [Vec2; 4] {
         [
             Vec2::new(self.x, self.y),
@@ -100,6 +130,7 @@ impl ColorRect {
     }
 }
 
+/// Texture stretched over a rectangle, fully transparent pixels are skipped.
 pub struct TextureRect {
     pub x: f32,
     pub y: f32,
@@ -1,4 +1,4 @@
-use crate::{geo::*, helpers::*, HEIGHT, WIDTH};
+use crate::{assets::BitmapFont, geo::*, helpers::*, HEIGHT, WIDTH};
 use glam::Vec2;
 use palette::Srgb;
 
@@ -131,6 +131,9 @@ impl Drawable for TextureRect {
                 let texture_index = (texture_x.floor() as usize
                     + texture_y.floor() as usize * self.texture_width)
                     * 4;
+                if self.texture[texture_index + 3] == 0 {
+                    continue;
+                }
 
                 let color = Srgb::new(
                     self.texture[texture_index] as f32 / 255.0,
@@ -142,3 +145,177 @@ impl Drawable for TextureRect {
         }
     }
 }
+
+/// Draw a single glyph, each font pixel becoming a `scale` sized square tinted by `color`.
+fn draw_glyph(
+    screen: &mut [u8],
+    font: &BitmapFont,
+    c: char,
+    x: f32,
+    y: f32,
+    scale: f32,
+    color: Srgb,
+) {
+    let Some(index) = font.glyph_index(c) else {
+        return;
+    };
+    for gy in 0..font.sheet.frame_height {
+        for gx in 0..font.sheet.frame_width {
+            let [r, g, b, a] = font.sheet.pixel(index, gx, gy);
+            if a > 0 {
+                ColorRect::new(
+                    x + gx as f32 * scale,
+                    y + gy as f32 * scale,
+                    scale,
+                    scale,
+                    Srgb::new(
+                        r as f32 / 255.0 * color.red,
+                        g as f32 / 255.0 * color.green,
+                        b as f32 / 255.0 * color.blue,
+                    ),
+                )
+                .draw(screen);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// String drawn from a bitmap font sheet.
+///
+/// `x` is the left edge, centre or right edge of every line depending on `align`.
+/// Lines are broken on `\n` and, when `max_width` is set, between words.
+pub struct Text<'a> {
+    pub font: &'a BitmapFont,
+    pub x: f32,
+    pub y: f32,
+    pub text: &'a str,
+    pub scale: f32,
+    pub color: Srgb,
+    pub align: Align,
+    pub monospace: bool,
+    pub max_width: Option<f32>,
+}
+
+impl<'a> Text<'a> {
+    pub fn new(font: &'a BitmapFont, x: f32, y: f32, text: &'a str) -> Self {
+        Self {
+            font,
+            x,
+            y,
+            text,
+            scale: 1.0,
+            color: Srgb::new(1.0, 1.0, 1.0),
+            align: Align::Left,
+            monospace: false,
+            max_width: None,
+        }
+    }
+
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn color(mut self, color: Srgb) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn monospace(mut self, monospace: bool) -> Self {
+        self.monospace = monospace;
+        self
+    }
+
+    pub fn wrap(mut self, max_width: f32) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    /// Lines after wrapping, as they will be drawn.
+    pub fn lines(&self) -> Vec<String> {
+        let max_width = self
+            .max_width
+            .map(|width| (width / self.scale).floor() as usize);
+        self.font.wrap(self.text, max_width, self.monospace)
+    }
+
+    /// Size of the drawn text in screen pixels.
+    pub fn size(&self) -> (f32, f32) {
+        let lines = self.lines();
+        let width = lines
+            .iter()
+            .map(|line| self.font.measure(line, self.monospace))
+            .max()
+            .unwrap_or(0);
+        (
+            width as f32 * self.scale,
+            (lines.len() * self.font.line_height()) as f32 * self.scale,
+        )
+    }
+}
+
+impl Drawable for Text<'_> {
+    fn draw(&self, screen: &mut [u8]) {
+        let line_height = self.font.line_height() as f32 * self.scale;
+        for (i, line) in self.lines().iter().enumerate() {
+            let width = self.font.measure(line, self.monospace) as f32 * self.scale;
+            let mut x = match self.align {
+                Align::Left => self.x,
+                Align::Center => (self.x - width / 2.0).floor(),
+                Align::Right => self.x - width,
+            };
+            let y = self.y + i as f32 * line_height;
+            for c in line.chars() {
+                draw_glyph(screen, self.font, c, x, y, self.scale, self.color);
+                x += self.font.advance(c, self.monospace) as f32 * self.scale;
+            }
+        }
+    }
+}
+
+/// Unsigned number drawn right-aligned so that its last digit ends at `x`.
+pub struct Number<'a> {
+    pub font: &'a BitmapFont,
+    pub x: f32,
+    pub y: f32,
+    pub value: u32,
+    pub scale: f32,
+    pub color: Srgb,
+}
+
+impl<'a> Number<'a> {
+    pub fn new(font: &'a BitmapFont, x: f32, y: f32, value: u32, scale: f32, color: Srgb) -> Self {
+        Self {
+            font,
+            x,
+            y,
+            value,
+            scale,
+            color,
+        }
+    }
+}
+
+impl Drawable for Number<'_> {
+    fn draw(&self, screen: &mut [u8]) {
+        let digits = self.value.to_string();
+        Text::new(self.font, self.x, self.y, &digits)
+            .scale(self.scale)
+            .color(self.color)
+            .align(Align::Right)
+            .monospace(true)
+            .draw(screen);
+    }
+}
@@ -1,3 +1,4 @@
+use std::path::Path;
 use std::sync::Arc;
 
 use egui::mutex::Mutex;
@@ -8,7 +9,12 @@ use pixels::{wgpu, PixelsContext};
 use winit::event_loop::EventLoopWindowTarget;
 use winit::window::Window;
 
-use crate::World;
+use wolfenstein_rs::{
+    automap::*, cheats::*, console::*, demo::*, difficulty::*, generator::*, levels::*, options::*,
+    rng::time_seed, save::*, World,
+};
+
+use crate::editor::Editor;
 
 pub(crate) struct Framework {
     // State for egui.
@@ -141,43 +147,217 @@ struct Gui {
     /// Only show the egui window when true.
     fps: Arc<Mutex<Fps>>,
     world: Arc<Mutex<World>>,
+    /// Save slot picked in the Settings window.
+    slot: usize,
+    /// Description of each save slot, refreshed after saving.
+    slots: Vec<Option<String>>,
+    /// File demos are recorded to and played from.
+    demo_path: String,
+    editor: Editor,
+    /// Seed and size for the next random level.
+    generator: GeneratorOptions,
+    /// Skill picked for the next new game.
+    difficulty: Difficulty,
 }
 
 impl Gui {
     fn new(fps: Arc<Mutex<Fps>>, world: Arc<Mutex<World>>) -> Self {
-        Self { fps, world }
+        Self {
+            fps,
+            world,
+            slot: 1,
+            slots: list_slots(),
+            demo_path: "demo.json".to_string(),
+            editor: Editor::default(),
+            difficulty: Difficulty::default(),
+            generator: GeneratorOptions {
+                seed: time_seed(),
+                ..Default::default()
+            },
+        }
     }
 
     fn ui(&mut self, ctx: &Context) {
         egui::Window::new("Settings").show(ctx, |ui| {
             ui.label(format!("FPS: {:.2}", self.fps.lock().avg()));
             ui.horizontal(|ui| {
-                ui.label("Speed:");
-                ui.add(DragValue::new(&mut self.world.lock().speed).speed(0.1));
+                let level = self.world.lock().level_name();
+                ui.label(format!("Level: {level}"));
+                if ui.button("Skip level").clicked() {
+                    let mut world = self.world.lock();
+                    if !world.refuse_while_recording("change levels") {
+                        world.complete_level(LevelExit::Normal);
+                    }
+                }
+                ui.checkbox(&mut self.editor.open, "Map editor");
+            });
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_label("Skill")
+                    .selected_text(self.difficulty.name())
+                    .show_ui(ui, |ui| {
+                        for difficulty in Difficulty::ALL {
+                            ui.selectable_value(
+                                &mut self.difficulty,
+                                difficulty,
+                                difficulty.name(),
+                            );
+                        }
+                    });
+                if ui.button("New game").clicked() {
+                    let mut world = self.world.lock();
+                    if !world.refuse_while_recording("start a new game") {
+                        world.new_game(0, time_seed(), self.difficulty);
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Seed:");
+                ui.add(DragValue::new(&mut self.generator.seed));
+                let range = MIN_SIZE..=MAX_SIZE;
+                ui.add(DragValue::new(&mut self.generator.width).clamp_range(range.clone()));
+                ui.label("x");
+                ui.add(DragValue::new(&mut self.generator.height).clamp_range(range));
+                if ui.button("New random level").clicked() {
+                    self.world.lock().random_level(&self.generator);
+                    self.generator.seed = self.generator.seed.wrapping_add(1);
+                }
+            });
+            ui.horizontal(|ui| {
+                let slot_name = |slot: usize, slots: &[Option<String>]| {
+                    let description = slots[slot].as_deref().unwrap_or("empty");
+                    format!("{slot}: {description}")
+                };
+                egui::ComboBox::from_label("Slot")
+                    .selected_text(slot_name(self.slot, &self.slots))
+                    .show_ui(ui, |ui| {
+                        for slot in 0..SAVE_SLOTS {
+                            let text = slot_name(slot, &self.slots);
+                            ui.selectable_value(&mut self.slot, slot, text);
+                        }
+                    });
+                if ui.button("Save").clicked() {
+                    self.world.lock().save_to_slot(self.slot);
+                    self.slots = list_slots();
+                }
+                if ui.button("Load").clicked() {
+                    self.world.lock().load_from_slot(self.slot);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Demo:");
+                ui.text_edit_singleline(&mut self.demo_path);
+                let mut world = self.world.lock();
+                let path = Path::new(&self.demo_path);
+                match world.demo {
+                    DemoState::Recording(_) => {
+                        if ui.button("Stop").clicked() {
+                            world.stop_recording(path);
+                        }
+                    }
+                    DemoState::Playing(..) => {
+                        ui.label("Playing");
+                    }
+                    DemoState::Idle => {
+                        let record = ui
+                            .add_enabled(!world.custom_map, egui::Button::new("Record"))
+                            .on_disabled_hover_text(
+                                "Demos can only be recorded on built-in levels",
+                            );
+                        if record.clicked() {
+                            world.start_recording(time_seed());
+                        }
+                        if ui.button("Play").clicked() {
+                            world.play_demo(path);
+                        }
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                let automap = &mut self.world.lock().automap;
+                egui::ComboBox::from_label("Map")
+                    .selected_text(automap.mode.name())
+                    .show_ui(ui, |ui| {
+                        for mode in [AutomapMode::Off, AutomapMode::Corner, AutomapMode::Full] {
+                            ui.selectable_value(&mut automap.mode, mode, mode.name());
+                        }
+                    });
+                ui.add(
+                    DragValue::new(&mut automap.zoom)
+                        .speed(0.1)
+                        .clamp_range(2.0..=32.0)
+                        .prefix("zoom "),
+                );
+                ui.checkbox(&mut automap.rotate, "Rotate");
+                ui.checkbox(&mut automap.show_enemies, "Enemies");
             });
             ui.horizontal(|ui| {
-                ui.label("Rot. speed:");
-                ui.add(DragValue::new(&mut self.world.lock().rotation_speed).speed(0.1));
+                let mut world = self.world.lock();
+                for cheat in Cheat::ALL {
+                    let mut on = world.cheats.is_on(cheat);
+                    if ui.checkbox(&mut on, cheat.name()).changed() {
+                        world.set_cheat(cheat, on);
+                    }
+                }
+                if ui.button("Give all").clicked() {
+                    world.give_all();
+                }
+            });
+            ui.label(format!(
+                "Explored: {}%",
+                self.world.lock().explored_percent()
+            ));
+            egui::Grid::new("cvars").show(ui, |ui| {
+                let mut world = self.world.lock();
+                for cvar in CVARS {
+                    ui.label(cvar.name).on_hover_text(cvar.description);
+                    let mut value = (cvar.get)(&world);
+                    let changed = match cvar.kind {
+                        CvarKind::Float { min, max } => ui
+                            .add(
+                                DragValue::new(&mut value)
+                                    .speed((max - min) / 200.0)
+                                    .clamp_range(min..=max),
+                            )
+                            .changed(),
+                        CvarKind::Bool => {
+                            let mut on = value != 0.0;
+                            let changed = ui.checkbox(&mut on, "").changed();
+                            value = on as u8 as f32;
+                            changed
+                        }
+                    };
+                    if changed {
+                        (cvar.set)(&mut world, value);
+                    }
+                    ui.end_row();
+                }
             });
+            if ui
+                .button("Reset options")
+                .on_hover_text(format!("Saved in {}", options_path().display()))
+                .clicked()
+            {
+                self.world.lock().apply_options(&Options::default());
+            }
+            let mut world = self.world.lock();
+            // Moving the player by hand would throw a demo off
+            let idle = matches!(world.demo, DemoState::Idle);
             ui.horizontal(|ui| {
                 ui.label("X:");
-                ui.add(DragValue::new(&mut self.world.lock().x).speed(0.1));
+                ui.add_enabled(idle, DragValue::new(&mut world.x).speed(0.1));
             });
             ui.horizontal(|ui| {
                 ui.label("Y:");
-                ui.add(DragValue::new(&mut self.world.lock().y).speed(0.1));
+                ui.add_enabled(idle, DragValue::new(&mut world.y).speed(0.1));
             });
             ui.horizontal(|ui| {
                 ui.label("Angle:");
-                ui.add(DragValue::new(&mut self.world.lock().theta).speed(0.01));
-                ui.label(format!("cos {:.2}", self.world.lock().theta.cos()));
-                ui.label(format!("sin {:.2}", self.world.lock().theta.sin()));
-            });
-            ui.horizontal(|ui| {
-                ui.label("FOV:");
-                ui.add(DragValue::new(&mut self.world.lock().fov).speed(0.1));
+                ui.add_enabled(idle, DragValue::new(&mut world.theta).speed(0.01));
+                ui.label(format!("cos {:.2}", world.theta.cos()));
+                ui.label(format!("sin {:.2}", world.theta.sin()));
             });
         });
+        self.editor.ui(ctx, &self.world);
         // egui::Window::new("Help").show(ctx, |ui| {
         //     ui.label("Move: WASD");
         //     ui.label("Rotate: QE");
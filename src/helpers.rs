@@ -3,7 +3,7 @@ use palette::Srgb;
 
 use crate::{
     map::{MAP_HEIGHT, MAP_WIDTH},
-    WIDTH,
+    HEIGHT, WIDTH,
 };
 
 pub fn clear_frame(frame: &mut [u8], color: Srgb) {
@@ -42,10 +42,10 @@ pub fn rgb_to_frame(rgb: &[Srgb]) -> Vec<u8> {
 }
 
 pub fn min_max_points(points: &[Vec2]) -> (f32, f32, f32, f32) {
-    let mut min_x = std::f32::MAX;
-    let mut max_x = std::f32::MIN;
-    let mut min_y = std::f32::MAX;
-    let mut max_y = std::f32::MIN;
+    let mut min_x = f32::MAX;
+    let mut max_x = f32::MIN;
+    let mut min_y = f32::MAX;
+    let mut max_y = f32::MIN;
     for point in points {
         min_x = min_x.min(point.x);
         max_x = max_x.max(point.x);
@@ -63,7 +63,7 @@ pub fn draw_pixel_brightness_rgb(
     color: Srgb,
     brightness: f32,
 ) {
-    if x >= 0.0 && x < WIDTH as f32 && y >= 0.0 && y < WIDTH as f32 {
+    if x >= 0.0 && x < WIDTH as f32 && y >= 0.0 && y < HEIGHT as f32 {
         let index = x as usize + y as usize * WIDTH as usize;
         if index < screen.len() {
             screen[index] = Srgb::new(
@@ -76,7 +76,7 @@ pub fn draw_pixel_brightness_rgb(
 }
 
 pub fn draw_pixel_rgb(screen: &mut [Srgb], x: f32, y: f32, color: Srgb) {
-    if x >= 0.0 && x < WIDTH as f32 && y >= 0.0 && y < WIDTH as f32 {
+    if x >= 0.0 && x < WIDTH as f32 && y >= 0.0 && y < HEIGHT as f32 {
         let index = x as usize + y as usize * WIDTH as usize;
         if index < screen.len() {
             screen[index] = color;
@@ -89,7 +89,7 @@ pub fn is_in_circle(p: Vec2, center: Vec2, radius: f32) -> bool {
 }
 
 pub fn draw_pixel_raw(screen: &mut [u8], x: f32, y: f32, color: Srgb) {
-    if x >= 0.0 && x < WIDTH as f32 && y >= 0.0 && y < WIDTH as f32 {
+    if x >= 0.0 && x < WIDTH as f32 && y >= 0.0 && y < HEIGHT as f32 {
         let index = x as usize + y as usize * WIDTH as usize;
         if (index * 4 + 2) < screen.len() {
             screen[index * 4] = (color.red * 255.0) as u8;
@@ -101,23 +101,20 @@ pub fn draw_pixel_raw(screen: &mut [u8], x: f32, y: f32, color: Srgb) {
 }
 
 pub fn draw_pixel_brightness_raw(screen: &mut [u8], x: f32, y: f32, color: Srgb, brightness: f32) {
-    if x >= 0.0 && x < WIDTH as f32 && y >= 0.0 && y < WIDTH as f32 {
+    if x >= 0.0 && x < WIDTH as f32 && y >= 0.0 && y < HEIGHT as f32 {
         let index = x as usize + y as usize * WIDTH as usize;
-        if index < screen.len() {
+        if index * 4 + 2 < screen.len() {
             screen[index * 4] = (((screen[index * 4] as f32 / 255.0) * (1.0 - brightness)
                 + color.red * brightness)
-                .min(1.0)
-                .max(0.0)
+                .clamp(0.0, 1.0)
                 * 255.0) as u8;
             screen[index * 4 + 1] = (((screen[index * 4 + 1] as f32 / 255.0) * (1.0 - brightness)
                 + color.green * brightness)
-                .min(1.0)
-                .max(0.0)
+                .clamp(0.0, 1.0)
                 * 255.0) as u8;
             screen[index * 4 + 2] = (((screen[index * 4 + 2] as f32 / 255.0) * (1.0 - brightness)
                 + color.blue * brightness)
-                .min(1.0)
-                .max(0.0)
+                .clamp(0.0, 1.0)
                 * 255.0) as u8;
             screen[index * 4 + 3] = 255;
         }
@@ -0,0 +1,190 @@
+use palette::Srgb;
+
+use crate::{geo::*, graphics::*, World, HEIGHT, STATUS_BAR_HEIGHT, VIEW_HEIGHT, WIDTH};
+
+const BAR_COLOR: Srgb = Srgb::new(0.0, 0.25, 0.5);
+const BOX_COLOR: Srgb = Srgb::new(0.0, 0.12, 0.3);
+const BEVEL_LIGHT: Srgb = Srgb::new(0.2, 0.45, 0.75);
+const BEVEL_DARK: Srgb = Srgb::new(0.0, 0.05, 0.15);
+const LABEL_COLOR: Srgb = Srgb::new(0.7, 0.8, 1.0);
+const NUMBER_COLOR: Srgb = Srgb::new(1.0, 1.0, 1.0);
+const MESSAGE_COLOR: Srgb = Srgb::new(1.0, 1.0, 0.6);
+const GOLD_KEY_COLOR: Srgb = Srgb::new(1.0, 0.8, 0.1);
+const SILVER_KEY_COLOR: Srgb = Srgb::new(0.75, 0.8, 0.85);
+
+/// How long messages stay on screen, in ticks.
+const MESSAGE_TICKS: u32 = 120;
+
+/// Line of text shown at the top of the view for a while.
+pub struct Message {
+    pub text: String,
+    pub ticks: u32,
+}
+
+impl Message {
+    pub fn new(text: String) -> Self {
+        Self {
+            text,
+            ticks: MESSAGE_TICKS,
+        }
+    }
+}
+
+/// Left edge and width of each status bar box, as in the original layout.
+const FLOOR_BOX: (f32, f32) = (8.0, 48.0);
+const SCORE_BOX: (f32, f32) = (64.0, 144.0);
+const LIVES_BOX: (f32, f32) = (216.0, 56.0);
+const FACE_BOX: (f32, f32) = (280.0, 48.0);
+const HEALTH_BOX: (f32, f32) = (336.0, 80.0);
+const AMMO_BOX: (f32, f32) = (424.0, 64.0);
+const KEYS_BOX: (f32, f32) = (496.0, 24.0);
+const WEAPON_BOX: (f32, f32) = (528.0, 104.0);
+
+impl World {
+    /// Draw the classic status bar below the 3D view.
+    pub fn draw_status_bar(&self, frame: &mut [u8]) {
+        let top = VIEW_HEIGHT as f32;
+        ColorRect::new(0.0, top, WIDTH as f32, STATUS_BAR_HEIGHT as f32, BAR_COLOR).draw(frame);
+        ColorRect::new(0.0, top, WIDTH as f32, 1.0, BEVEL_LIGHT).draw(frame);
+
+        let font = &self.assets.font;
+        let number_y = top + 22.0;
+        let stats = &self.stats;
+
+        self.draw_box(frame, FLOOR_BOX, "FLOOR");
+        Number::new(
+            font,
+            FLOOR_BOX.0 + FLOOR_BOX.1 - 4.0,
+            number_y,
+            self.level,
+            2.0,
+            NUMBER_COLOR,
+        )
+        .draw(frame);
+
+        self.draw_box(frame, SCORE_BOX, "SCORE");
+        Number::new(
+            font,
+            SCORE_BOX.0 + SCORE_BOX.1 - 4.0,
+            number_y,
+            stats.score,
+            2.0,
+            NUMBER_COLOR,
+        )
+        .draw(frame);
+
+        self.draw_box(frame, LIVES_BOX, "LIVES");
+        Number::new(
+            font,
+            LIVES_BOX.0 + LIVES_BOX.1 - 4.0,
+            number_y,
+            stats.lives,
+            2.0,
+            NUMBER_COLOR,
+        )
+        .draw(frame);
+
+        self.draw_box(frame, FACE_BOX, "");
+        let face = self.assets.faces.frame(self.face.frame(stats.health));
+        TextureRect::new(
+            FACE_BOX.0 + 4.0,
+            top + 4.0,
+            FACE_BOX.1 - 8.0,
+            STATUS_BAR_HEIGHT as f32 - 8.0,
+            face,
+            self.assets.faces.frame_width,
+            self.assets.faces.frame_height,
+        )
+        .draw(frame);
+
+        self.draw_box(frame, HEALTH_BOX, "HEALTH");
+        Number::new(
+            font,
+            HEALTH_BOX.0 + HEALTH_BOX.1 - 20.0,
+            number_y,
+            stats.health,
+            2.0,
+            NUMBER_COLOR,
+        )
+        .draw(frame);
+        Text::new(font, HEALTH_BOX.0 + HEALTH_BOX.1 - 18.0, number_y, "%")
+            .scale(2.0)
+            .color(NUMBER_COLOR)
+            .draw(frame);
+
+        self.draw_box(frame, AMMO_BOX, "AMMO");
+        Number::new(
+            font,
+            AMMO_BOX.0 + AMMO_BOX.1 - 4.0,
+            number_y,
+            stats.ammo,
+            2.0,
+            NUMBER_COLOR,
+        )
+        .draw(frame);
+
+        self.draw_box(frame, KEYS_BOX, "");
+        self.draw_key(
+            frame,
+            KEYS_BOX.0 + 8.0,
+            top + 8.0,
+            stats.keys.gold,
+            GOLD_KEY_COLOR,
+        );
+        self.draw_key(
+            frame,
+            KEYS_BOX.0 + 8.0,
+            top + 26.0,
+            stats.keys.silver,
+            SILVER_KEY_COLOR,
+        );
+
+        self.draw_box(frame, WEAPON_BOX, "WEAPON");
+        Text::new(
+            font,
+            WEAPON_BOX.0 + WEAPON_BOX.1 / 2.0,
+            number_y + 4.0,
+            stats.weapon.name(),
+        )
+        .color(NUMBER_COLOR)
+        .align(Align::Center)
+        .draw(frame);
+    }
+
+    /// Draw the current message, if any, centred at the top of the view.
+    pub fn draw_message(&self, frame: &mut [u8]) {
+        if let Some(message) = &self.message {
+            Text::new(&self.assets.font, WIDTH as f32 / 2.0, 8.0, &message.text)
+                .scale(2.0)
+                .color(MESSAGE_COLOR)
+                .align(Align::Center)
+                .wrap(WIDTH as f32 - 32.0)
+                .draw(frame);
+        }
+    }
+
+    /// Sunken box with an optional caption along its top edge.
+    fn draw_box(&self, frame: &mut [u8], (x, width): (f32, f32), label: &str) {
+        let top = VIEW_HEIGHT as f32 + 4.0;
+        let height = (HEIGHT - VIEW_HEIGHT) as f32 - 8.0;
+        ColorRect::new(x, top, width, height, BOX_COLOR).draw(frame);
+        ColorRect::new(x, top, width, 1.0, BEVEL_DARK).draw(frame);
+        ColorRect::new(x, top + height - 1.0, width, 1.0, BEVEL_LIGHT).draw(frame);
+        Text::new(&self.assets.font, x + width / 2.0, top + 3.0, label)
+            .color(LABEL_COLOR)
+            .align(Align::Center)
+            .draw(frame);
+    }
+
+    /// Small key icon, only outlined when the key isn't held.
+    fn draw_key(&self, frame: &mut [u8], x: f32, y: f32, held: bool, color: Srgb) {
+        if held {
+            ColorRect::new(x, y, 8.0, 6.0, color).draw(frame);
+            ColorRect::new(x + 3.0, y + 6.0, 2.0, 8.0, color).draw(frame);
+            ColorRect::new(x + 5.0, y + 10.0, 2.0, 2.0, color).draw(frame);
+        } else {
+            ColorRect::new(x, y, 8.0, 1.0, BEVEL_LIGHT).draw(frame);
+            ColorRect::new(x, y + 13.0, 8.0, 1.0, BEVEL_LIGHT).draw(frame);
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use palette::Srgb;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actors::Actor, geo::*, graphics::*, levels::LevelExit, map::*, objects::Object, Controls,
+    World, TICKS_PER_SECOND, VIEW_HEIGHT, WIDTH,
+};
+
+/// Points for every second under par.
+const PAR_BONUS: u32 = 500;
+/// Points for each ratio that reaches 100%.
+const PERFECT_BONUS: u32 = 10000;
+/// Ratio points added per tick while counting up.
+const COUNT_STEP: u32 = 2;
+
+const BACKGROUND_COLOR: Srgb = Srgb::new(0.0, 0.25, 0.25);
+const TITLE_COLOR: Srgb = Srgb::new(1.0, 1.0, 1.0);
+const TEXT_COLOR: Srgb = Srgb::new(1.0, 1.0, 0.6);
+const HINT_COLOR: Srgb = Srgb::new(0.6, 0.8, 0.8);
+const CHEAT_COLOR: Srgb = Srgb::new(0.9, 0.3, 0.3);
+
+/// What the player found and did on the current level.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Tally {
+    pub kills: u32,
+    pub total_kills: u32,
+    pub secrets: u32,
+    pub total_secrets: u32,
+    pub treasure: u32,
+    pub total_treasure: u32,
+    /// Ticks spent playing the level.
+    pub ticks: u32,
+}
+
+impl Tally {
+    /// Empty tally for a freshly loaded level.
+    pub fn new(map: &Map, actors: &[Actor], objects: &[Object]) -> Self {
+        Self {
+            total_kills: actors.len() as u32,
+            total_secrets: map.count_objects(PUSHWALL) as u32,
+            total_treasure: objects
+                .iter()
+                .filter(|object| object.kind.treasure_points().is_some())
+                .count() as u32,
+            ..Default::default()
+        }
+    }
+
+    pub fn seconds(&self) -> u32 {
+        self.ticks / TICKS_PER_SECOND
+    }
+
+    /// Kill, secret and treasure ratios in percent. A level without any counts as 0%.
+    pub fn ratios(&self) -> [u32; 3] {
+        [
+            ratio(self.kills, self.total_kills),
+            ratio(self.secrets, self.total_secrets),
+            ratio(self.treasure, self.total_treasure),
+        ]
+    }
+}
+
+fn ratio(count: u32, total: u32) -> u32 {
+    (count * 100).checked_div(total).unwrap_or(0)
+}
+
+/// The "floor completed" screen, counting up each ratio in turn.
+#[derive(Debug, Clone)]
+pub struct Intermission {
+    pub floor: u32,
+    pub exit: LevelExit,
+    pub tally: Tally,
+    /// Par time in seconds.
+    pub par: u32,
+    /// Ratio currently counting up, 3 once everything has been shown.
+    pub stage: usize,
+    /// Value the current ratio has counted up to.
+    pub count: u32,
+}
+
+impl Intermission {
+    pub fn new(floor: u32, exit: LevelExit, tally: Tally, par: u32) -> Self {
+        Self {
+            floor,
+            exit,
+            tally,
+            par,
+            stage: 0,
+            count: 0,
+        }
+    }
+
+    pub fn time_bonus(&self) -> u32 {
+        self.par.saturating_sub(self.tally.seconds()) * PAR_BONUS
+    }
+
+    /// Ratios as currently displayed.
+    pub fn shown_ratios(&self) -> [u32; 3] {
+        let mut ratios = self.tally.ratios();
+        for (i, ratio) in ratios.iter_mut().enumerate() {
+            if i == self.stage {
+                *ratio = self.count;
+            } else if i > self.stage {
+                *ratio = 0;
+            }
+        }
+        ratios
+    }
+
+    /// Bonus as currently displayed, the full bonus once done.
+    pub fn bonus(&self) -> u32 {
+        let perfect = self
+            .tally
+            .ratios()
+            .iter()
+            .take(self.stage)
+            .filter(|&&ratio| ratio == 100)
+            .count() as u32;
+        self.time_bonus() + perfect * PERFECT_BONUS
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.stage >= 3
+    }
+
+    /// Jump to the end of the counting.
+    pub fn skip(&mut self) {
+        self.stage = 3;
+        self.count = 0;
+    }
+
+    pub fn update(&mut self) {
+        if self.is_done() {
+            return;
+        }
+        let target = self.tally.ratios()[self.stage];
+        self.count = (self.count + COUNT_STEP).min(target);
+        if self.count == target {
+            self.stage += 1;
+            self.count = 0;
+        }
+    }
+}
+
+/// Format seconds as `MM:SS`.
+fn format_time(seconds: u32) -> String {
+    format!("{:02}:{:02}", (seconds / 60).min(99), seconds % 60)
+}
+
+impl World {
+    /// Replace gameplay with the intermission for the level just completed.
+    pub fn start_intermission(&mut self, exit: LevelExit) {
+        self.intermission = Some(Intermission::new(
+            self.level,
+            exit,
+            self.tally.clone(),
+            self.map.par,
+        ));
+        self.exit = None;
+    }
+
+    /// Count up the ratios; activate skips the counting, then moves on to the next level.
+    pub fn update_intermission(&mut self, controls: &Controls) {
+        let Some(intermission) = &mut self.intermission else {
+            return;
+        };
+        intermission.update();
+        if !controls.activate {
+            return;
+        }
+        if !intermission.is_done() {
+            intermission.skip();
+            return;
+        }
+        let exit = intermission.exit;
+        self.stats.score += intermission.bonus();
+        self.intermission = None;
+        self.complete_level(exit);
+    }
+
+    pub fn draw_intermission(&self, frame: &mut [u8]) {
+        let Some(intermission) = &self.intermission else {
+            return;
+        };
+        ColorRect::new(0.0, 0.0, WIDTH as f32, VIEW_HEIGHT as f32, BACKGROUND_COLOR).draw(frame);
+        let font = &self.assets.font;
+        let center = WIDTH as f32 / 2.0;
+        let label_x = center + 40.0;
+        let value_x = center + 200.0;
+
+        let title = format!("FLOOR {}\nCOMPLETED", intermission.floor);
+        Text::new(font, center, 24.0, &title)
+            .scale(3.0)
+            .color(TITLE_COLOR)
+            .align(Align::Center)
+            .draw(frame);
+
+        let [kills, secrets, treasure] = intermission.shown_ratios();
+        let rows = [
+            ("BONUS", intermission.bonus().to_string()),
+            ("TIME", format_time(intermission.tally.seconds())),
+            ("PAR", format_time(intermission.par)),
+            ("KILL RATIO", format!("{kills}%")),
+            ("SECRET RATIO", format!("{secrets}%")),
+            ("TREASURE RATIO", format!("{treasure}%")),
+        ];
+        for (i, (label, value)) in rows.iter().enumerate() {
+            let y = 104.0 + i as f32 * 26.0;
+            Text::new(font, label_x, y, label)
+                .scale(2.0)
+                .color(TEXT_COLOR)
+                .align(Align::Right)
+                .draw(frame);
+            Text::new(font, value_x, y, value)
+                .scale(2.0)
+                .color(TEXT_COLOR)
+                .align(Align::Right)
+                .monospace(true)
+                .draw(frame);
+        }
+
+        if self.stats.cheated {
+            Text::new(font, center, VIEW_HEIGHT as f32 - 52.0, "CHEATS USED")
+                .color(CHEAT_COLOR)
+                .align(Align::Center)
+                .draw(frame);
+        }
+        if intermission.is_done() {
+            Text::new(
+                font,
+                center,
+                VIEW_HEIGHT as f32 - 28.0,
+                "Press space to continue",
+            )
+            .scale(2.0)
+            .color(HINT_COLOR)
+            .align(Align::Center)
+            .draw(frame);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use crate::map::Map;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelExit {
+    Normal,
+    Secret,
+}
+
+/// A floor of an episode, and where its exits lead.
+pub struct Level {
+    pub episode: u32,
+    pub floor: u32,
+    pub source: &'static str,
+    /// Index in `LEVELS` of the level behind the normal exit, `None` at the end of the episode.
+    pub next: Option<usize>,
+    /// Index in `LEVELS` of the bonus floor behind the secret exit.
+    pub secret: Option<usize>,
+    /// Name of the music track in `TRACKS` played on this floor.
+    pub music: &'static str,
+}
+
+impl Level {
+    /// Short name like `e1m2`, as used by the console.
+    pub fn name(&self) -> String {
+        format!("e{}m{}", self.episode, self.floor)
+    }
+
+    pub fn map(&self) -> Map {
+        Map::parse(self.source).expect("built-in maps are valid")
+    }
+
+    /// Level reached through the given exit.
+    pub fn exit_to(&self, exit: LevelExit) -> Option<usize> {
+        match exit {
+            LevelExit::Normal => self.next,
+            LevelExit::Secret => self.secret.or(self.next),
+        }
+    }
+}
+
+pub const LEVELS: [Level; 4] = [
+    Level {
+        episode: 1,
+        floor: 1,
+        source: include_str!("../assets/maps/e1m1.map"),
+        next: Some(1),
+        secret: Some(3),
+        music: "march",
+    },
+    Level {
+        episode: 1,
+        floor: 2,
+        source: include_str!("../assets/maps/e1m2.map"),
+        next: Some(2),
+        secret: None,
+        music: "march",
+    },
+    Level {
+        episode: 1,
+        floor: 3,
+        source: include_str!("../assets/maps/e1m3.map"),
+        next: None,
+        secret: None,
+        music: "alarm",
+    },
+    Level {
+        episode: 1,
+        floor: 10,
+        source: include_str!("../assets/maps/e1m10.map"),
+        next: Some(1),
+        secret: None,
+        music: "bonus",
+    },
+];
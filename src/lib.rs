@@ -0,0 +1,1074 @@
+//! Engine of wolfenstein-rs: maps, the raycaster, drawing into an RGBA frame and the
+//! simulation of the world, with no window attached. The game binary puts a winit window,
+//! input and the egui tools on top, and other tools can drive a `World` the same way.
+#![deny(clippy::all)]
+#![forbid(unsafe_code)]
+#![allow(dead_code, unused_assignments)]
+
+use std::f32::consts::{PI, TAU};
+use std::path::PathBuf;
+
+use glam::Vec2;
+use palette::Srgb;
+
+pub mod actors;
+pub mod assets;
+pub mod audio;
+pub mod automap;
+pub mod cheats;
+pub mod console;
+pub mod demo;
+pub mod difficulty;
+pub mod doors;
+pub mod exploration;
+pub mod generator;
+pub mod geo;
+pub mod graphics;
+pub mod helpers;
+pub mod hud;
+pub mod intermission;
+pub mod levels;
+pub mod map;
+pub mod menu;
+pub mod music;
+pub mod objects;
+pub mod opl;
+pub mod options;
+pub mod player;
+pub mod pushwalls;
+pub mod rng;
+pub mod save;
+pub mod weapons;
+
+use actors::*;
+use assets::*;
+use audio::*;
+use automap::*;
+use cheats::*;
+use console::*;
+use demo::*;
+use difficulty::*;
+use doors::*;
+use geo::*;
+use helpers::*;
+use hud::*;
+use intermission::*;
+use levels::*;
+use map::*;
+use menu::*;
+use music::*;
+use objects::*;
+use options::*;
+use player::*;
+use pushwalls::*;
+use rng::*;
+
+pub const WIDTH: u32 = 1920 / 3;
+pub const HEIGHT: u32 = 1080 / 3;
+pub const STATUS_BAR_HEIGHT: u32 = 48;
+/// Height of the 3D view above the status bar.
+pub const VIEW_HEIGHT: u32 = HEIGHT - STATUS_BAR_HEIGHT;
+/// Height on screen of something one cell tall, one world unit away.
+pub const PROJECTION: f32 = VIEW_HEIGHT as f32 * 50.0;
+/// Height of the player's eyes above the floor in cells, standing and crouching.
+pub const EYE_HEIGHT: f32 = 0.5;
+pub const CROUCH_EYE_HEIGHT: f32 = 0.3;
+/// How far the view bobs up and down while walking at full speed, in cells.
+const BOB_HEIGHT: f32 = 0.02;
+/// Bob cycles per world unit walked; each cycle is two steps.
+const BOB_RATE: f32 = 1.0 / 96.0;
+/// Fixed simulation rate of `World::update`.
+pub const TICKS_PER_SECOND: u32 = 60;
+
+/// Input for one tick of `World::update`, filled in from the keyboard and mouse or a demo.
+#[derive(Debug, Default, Clone)]
+pub struct Controls {
+    pub forward: bool,
+    pub backward: bool,
+    pub left: bool,
+    pub right: bool,
+    pub strafe_left: bool,
+    pub strafe_right: bool,
+    /// Open doors, only set for the tick the key was pressed on.
+    pub activate: bool,
+    pub fire: bool,
+    pub crouch: bool,
+    pub look_up: bool,
+    pub look_down: bool,
+    /// Weapon picked with the number keys, only set for the tick the key was pressed on.
+    pub weapon: Option<Weapon>,
+    /// Horizontal mouse movement since the last update, in pixels.
+    pub mouse_dx: f32,
+    /// Vertical mouse movement since the last update, in pixels, positive downwards.
+    pub mouse_dy: f32,
+    pub quick_save: bool,
+    pub quick_load: bool,
+}
+
+impl Controls {
+    /// Clear one-shot actions once an update has seen them.
+    pub fn consume_actions(&mut self) {
+        self.activate = false;
+        self.weapon = None;
+        self.quick_save = false;
+        self.quick_load = false;
+        self.mouse_dx = 0.0;
+        self.mouse_dy = 0.0;
+    }
+}
+
+/// The whole game: the level being played, the player, enemies and everything drawn on
+/// top. `update` runs one tick and `draw` renders a `WIDTH` by `HEIGHT` RGBA frame.
+pub struct World {
+    pub speed: f32,
+    pub rotation_speed: f32,
+    /// Radians turned per pixel of mouse movement.
+    pub mouse_sensitivity: f32,
+    pub x: f32,
+    pub y: f32,
+    pub theta: f32,
+    /// How far up the player looks in radians, negative when looking down.
+    pub pitch: f32,
+    /// Furthest the view can look up or down, in degrees.
+    pub max_pitch: f32,
+    /// Height of the player's eyes above the floor, in cells.
+    pub eye_height: f32,
+    /// Floor height the view is at, following the floor under the player a little behind
+    /// to smooth out steps.
+    pub view_floor: f32,
+    pub fov: f32,
+    pub assets: Assets,
+    /// Index of the current level in `LEVELS`.
+    pub level_index: usize,
+    /// Whether the map is a generated or loaded one rather than `LEVELS[level_index]`.
+    pub custom_map: bool,
+    /// Floor number shown in the status bar.
+    pub level: u32,
+    pub stats: PlayerStats,
+    pub face: Face,
+    pub ticks: u64,
+    pub map: Map,
+    pub doors: Vec<Door>,
+    pub objects: Vec<Object>,
+    pub actors: Vec<Actor>,
+    pub pushwalls: Vec<PushWall>,
+    pub message: Option<Message>,
+    /// Exit taken, and ticks left before the intermission starts.
+    pub exit: Option<(LevelExit, u32)>,
+    pub tally: Tally,
+    pub intermission: Option<Intermission>,
+    /// Ticks left before the current weapon can attack again.
+    pub attack_ticks: u32,
+    pub rng: Rng,
+    pub demo: DemoState,
+    pub automap: Automap,
+    /// Cells any ray has gone through so far, row by row.
+    pub seen: Vec<bool>,
+    /// Distance in cells at which walls fade to black, 0 for no fog.
+    pub fog: f32,
+    /// Share of the screen columns rays are cast for, the rest repeating their neighbour.
+    pub render_scale: f32,
+    /// Whether the view and weapon move with the player's steps. Off for less motion.
+    pub view_bob: bool,
+    /// How much the view bobs up and down, 1 being normal.
+    pub bob_strength: f32,
+    /// How much the weapon sways while walking, 1 being normal.
+    pub sway_strength: f32,
+    /// How far through a bob cycle the player is, in cycles.
+    pub bob_phase: f32,
+    /// How fast the player has been walking lately, from 0 standing to 1 at full speed.
+    pub bob_amount: f32,
+    pub cheats: Cheats,
+    pub difficulty: Difficulty,
+    pub console: Console,
+    /// File to save the next drawn frame to.
+    pub screenshot: Option<PathBuf>,
+    pub audio: Audio,
+    pub menu: Menu,
+    /// Options as last loaded or written, to tell when they change.
+    pub options: Options,
+}
+
+/// Where a ray met a wall, door or raised floor.
+#[derive(Debug, Clone)]
+pub struct RayHit {
+    /// Wall tile, 0 for the side of a raised floor.
+    pub tile: u8,
+    pub cell: (usize, usize),
+    pub point: Point2,
+    pub side: u32,
+    /// Position across the face of the tile, from 0 to 1.
+    pub offset: f32,
+    /// Distance to where the ray entered the cell, and to where it left it. Doors are flat,
+    /// leaving where they were entered.
+    pub dist: f32,
+    pub exit: f32,
+}
+
+/// For each column of the view, how far the walls in it are and the row above which things
+/// behind each still show, nearest first.
+pub type ColumnClips = Vec<Vec<(f32, f32)>>;
+
+/// A see-through wall met by the rays of `columns`, drawn once everything behind it is.
+#[derive(Debug, Clone)]
+pub struct MaskedSlice {
+    pub columns: std::ops::Range<u32>,
+    pub hit: RayHit,
+    /// Row from which down nearer walls hide it.
+    pub clip: f32,
+}
+
+/// Texture drawn on the sides of raised floors.
+const STEP_TEXTURE: u8 = 4;
+/// Color of the tops of walls and floors seen from above.
+const TOP_COLOR: Srgb = Srgb::new(0.25, 0.25, 0.25);
+
+/// How far in front of the player doors can be opened from.
+const USE_DISTANCE: f32 = 20.0;
+/// How close the player has to walk to an object to pick it up.
+const PICKUP_DISTANCE: f32 = 10.0;
+/// Ticks between flipping an elevator switch and leaving the level.
+const EXIT_TICKS: u32 = 30;
+pub const MAX_AMMO: u32 = 99;
+
+impl World {
+    /// A world on the first level, with default options.
+    pub fn new(assets: Assets) -> Self {
+        let options = Options::default();
+        let mut world = Self {
+            x: WIDTH as f32 / 2.0,
+            y: HEIGHT as f32 / 2.0,
+            theta: -PI / 2.0,
+            pitch: 0.0,
+            max_pitch: options.controls.max_pitch,
+            eye_height: EYE_HEIGHT,
+            view_floor: 0.0,
+            speed: options.controls.speed,
+            rotation_speed: options.controls.rotation_speed,
+            mouse_sensitivity: options.controls.mouse_sensitivity,
+            fov: options.video.fov,
+            assets,
+            level_index: 0,
+            custom_map: false,
+            level: 1,
+            stats: PlayerStats::default(),
+            face: Face::default(),
+            ticks: 0,
+            map: Map::new(0, 0),
+            doors: vec![],
+            objects: vec![],
+            actors: vec![],
+            pushwalls: vec![],
+            message: None,
+            exit: None,
+            tally: Tally::default(),
+            intermission: None,
+            attack_ticks: 0,
+            rng: Rng::new(0),
+            demo: DemoState::Idle,
+            automap: Automap::default(),
+            seen: vec![],
+            fog: options.video.fog,
+            render_scale: options.video.render_scale,
+            view_bob: options.video.view_bob,
+            bob_strength: options.video.bob_strength,
+            sway_strength: options.video.sway_strength,
+            bob_phase: 0.0,
+            bob_amount: 0.0,
+            cheats: Cheats::default(),
+            difficulty: Difficulty::default(),
+            console: Console::default(),
+            screenshot: None,
+            audio: Audio::default(),
+            menu: Menu::default(),
+            options: Options::default(),
+        };
+        world.apply_options(&options);
+        world.load_level(0);
+        world
+    }
+
+    /// Replace the map with another level, keeping the player's stats but not the keys.
+    pub fn load_level(&mut self, index: usize) {
+        let level = &LEVELS[index];
+        self.level_index = index;
+        self.level = level.floor;
+        self.start_map(level.map());
+        self.custom_map = false;
+        if let Some(track) = Track::find(level.music) {
+            self.audio.play_music(track);
+        }
+    }
+
+    /// Start over on `map` from its player start, keeping the player's stats but not the keys.
+    pub fn start_map(&mut self, map: Map) {
+        self.map = map;
+        self.custom_map = true;
+        self.doors = find_doors(&self.map);
+        self.objects = spawn_objects(&self.map);
+        self.actors = spawn_actors(&self.map, self.difficulty);
+        self.pushwalls = vec![];
+        self.seen = vec![false; self.map.width * self.map.height];
+        self.tally = Tally::new(&self.map, &self.actors, &self.objects);
+        self.stats.keys = Keys::default();
+        self.message = None;
+        self.exit = None;
+        self.intermission = None;
+        self.attack_ticks = 0;
+        if let Some((x, y, theta)) = self.map.player_start() {
+            self.x = x;
+            self.y = y;
+            self.theta = theta;
+        }
+        self.pitch = 0.0;
+        self.bob_amount = 0.0;
+        self.view_floor = self.floor_under(self.x, self.y);
+    }
+
+    /// Swap in another map for the current level, e.g. while editing it.
+    /// The player stays put unless that spot is now outside the map or inside a wall.
+    pub fn set_map(&mut self, map: Map) {
+        let resized = map.width != self.map.width || map.height != self.map.height;
+        self.map = map;
+        self.doors = find_doors(&self.map);
+        self.objects = spawn_objects(&self.map);
+        self.actors = spawn_actors(&self.map, self.difficulty);
+        self.pushwalls = vec![];
+        self.tally = Tally::new(&self.map, &self.actors, &self.objects);
+        if resized {
+            self.seen = vec![false; self.map.width * self.map.height];
+        }
+        if self.will_hit_obstacle(self.x, self.y).is_some() {
+            if let Some((x, y, theta)) = self.map.player_start() {
+                self.x = x;
+                self.y = y;
+                self.theta = theta;
+            }
+        }
+    }
+
+    /// Short name of the level being played, like `E1M2`, or the name of a custom map.
+    pub fn level_name(&self) -> String {
+        if self.custom_map {
+            match self.map.name.as_str() {
+                "" => "Custom map".to_string(),
+                name => name.to_string(),
+            }
+        } else {
+            format!("E{}M{}", LEVELS[self.level_index].episode, self.level)
+        }
+    }
+
+    /// Move on to the level behind `exit`, or start over after the last one.
+    pub fn complete_level(&mut self, exit: LevelExit) {
+        match LEVELS[self.level_index].exit_to(exit) {
+            Some(next) => self.load_level(next),
+            None => {
+                self.stats = PlayerStats::default();
+                self.load_level(0);
+                self.message = Some(Message::new(format!(
+                    "Episode {} complete!",
+                    LEVELS[self.level_index].episode
+                )));
+            }
+        }
+    }
+
+    /// Lose a life and restart the level, or start over once out of lives.
+    pub fn die(&mut self) {
+        if self.stats.lives == 0 {
+            self.stats = PlayerStats::default();
+            self.load_level(0);
+            self.message = Some(Message::new("Game over".to_string()));
+            return;
+        }
+        self.stats = PlayerStats {
+            score: self.stats.score,
+            lives: self.stats.lives - 1,
+            cheated: self.stats.cheated,
+            ..PlayerStats::default()
+        };
+        self.load_level(self.level_index);
+    }
+
+    pub fn door_at(&self, x: usize, y: usize) -> Option<&Door> {
+        self.doors.iter().find(|door| door.x == x && door.y == y)
+    }
+
+    pub fn wall_texture(&self, tile: u8) -> &Vec<Vec<u8>> {
+        match tile {
+            DOOR => &self.assets.doors[0],
+            GOLD_DOOR => &self.assets.doors[1],
+            SILVER_DOOR => &self.assets.doors[2],
+            ELEVATOR_SWITCH | SECRET_ELEVATOR_SWITCH => {
+                &self.assets.elevator[self.exit.is_some() as usize]
+            }
+            // Asset packs may have fewer textures than the map uses, fall back to the first
+            tile if is_masked_wall(tile) => self
+                .assets
+                .masked
+                .get((tile - FIRST_MASKED_WALL) as usize)
+                .unwrap_or(&self.assets.textures[0]),
+            _ => self
+                .assets
+                .textures
+                .get((tile as usize).saturating_sub(1))
+                .unwrap_or(&self.assets.textures[0]),
+        }
+    }
+
+    /// Whether the player may step onto `x`, `y`, anywhere on the map with noclip.
+    pub fn can_move_to(&self, x: f32, y: f32) -> bool {
+        if self.cheats.noclip {
+            return self.map.cell_at(x, y).is_some();
+        }
+        let step_up = match (self.map.cell_at(self.x, self.y), self.map.cell_at(x, y)) {
+            (Some((from_x, from_y)), Some((to_x, to_y))) => self
+                .map
+                .floor_steps(to_x, to_y)
+                .saturating_sub(self.map.floor_steps(from_x, from_y)),
+            _ => 0,
+        };
+        step_up <= MAX_STEP && self.will_hit_obstacle(x, y).is_none()
+    }
+
+    /// Height of the floor at `x`, `y` in cells.
+    pub fn floor_under(&self, x: f32, y: f32) -> f32 {
+        self.map
+            .cell_at(x, y)
+            .map_or(0.0, |(x, y)| self.map.floor_height(x, y))
+    }
+
+    /// Height of the player's eyes in cells, which is where the view is drawn from.
+    pub fn eye_z(&self) -> f32 {
+        self.view_floor + self.eye_height + self.view_bob_height()
+    }
+
+    /// Follow how far the player walked this tick, `walked` world units, with the bob.
+    fn update_bob(&mut self, walked: f32) {
+        let amount = (walked / self.speed.max(0.01)).min(1.0);
+        self.bob_amount += (amount - self.bob_amount) * 0.1;
+        self.bob_phase = (self.bob_phase + walked * BOB_RATE).fract();
+    }
+
+    /// How far the view is above or below the eye height because of the bob, in cells.
+    pub fn view_bob_height(&self) -> f32 {
+        if !self.view_bob {
+            return 0.0;
+        }
+        (self.bob_phase * TAU * 2.0).sin() * BOB_HEIGHT * self.bob_strength * self.bob_amount
+    }
+
+    /// Look up or down with the keys and the mouse, within `max_pitch`.
+    fn update_pitch(&mut self, controls: &Controls) {
+        if controls.look_up {
+            self.pitch += self.rotation_speed;
+        }
+        if controls.look_down {
+            self.pitch -= self.rotation_speed;
+        }
+        self.pitch -= controls.mouse_dy * self.mouse_sensitivity;
+        let max_pitch = self.max_pitch.to_radians();
+        self.pitch = self.pitch.clamp(-max_pitch, max_pitch);
+    }
+
+    /// Row of the view the horizon is on, moved down when looking up and up when looking
+    /// down by shearing the view rather than tilting it.
+    pub fn horizon(&self) -> f32 {
+        VIEW_HEIGHT as f32 * (0.5 + self.pitch.tan())
+    }
+
+    /// Move the view towards the floor under the player and the height they stand or
+    /// crouch at.
+    fn update_view_height(&mut self, controls: &Controls) {
+        let floor = self.floor_under(self.x, self.y);
+        self.view_floor += (floor - self.view_floor) * 0.3;
+        if (floor - self.view_floor).abs() < 0.01 {
+            self.view_floor = floor;
+        }
+        let eye = if controls.crouch {
+            CROUCH_EYE_HEIGHT
+        } else {
+            EYE_HEIGHT
+        };
+        self.eye_height += (eye - self.eye_height).clamp(-0.04, 0.04);
+    }
+
+    pub fn will_hit_obstacle(&self, x: f32, y: f32) -> Option<u8> {
+        let Some((map_x, map_y)) = self.map.cell_at(x, y) else {
+            return Some(0);
+        };
+        let tile = self.map.tile(map_x, map_y);
+        if tile == 0 {
+            return None;
+        }
+        match self.door_at(map_x, map_y) {
+            Some(door) if door.is_passable() => None,
+            _ => Some(tile),
+        }
+    }
+
+    /// Cast a ray, calling `visit` with every wall, door and raised floor it meets nearest
+    /// first, until `visit` returns false or the ray leaves the map. Every cell the ray goes
+    /// through is flagged in `seen`.
+    pub fn ray_hits(
+        &self,
+        start: Point2,
+        angle: f32,
+        seen: &mut [bool],
+        mut visit: impl FnMut(&RayHit) -> bool,
+    ) {
+        let mut x = start.x;
+        let mut y = start.y;
+        let dx = angle.cos();
+        let dy = angle.sin();
+        let mut step_x = 1;
+        let mut step_y = 1;
+        let mut side = 0;
+        let mut side_dist_x = 0.0;
+        let mut side_dist_y = 0.0;
+        let delta_dist_x = (1.0 / dx).abs();
+        let delta_dist_y = (1.0 / dy).abs();
+        let start_cell = self.map.cell_at(x, y);
+        // Hit whose cell the ray is still going through, waiting for its exit
+        let mut inside: Option<RayHit> = None;
+        // Door cell the ray already went through the open part of, or hit
+        let mut passed_door = None;
+        if dx < 0.0 {
+            step_x = -1;
+            side_dist_x = (x - (x as i32 as f32)) * delta_dist_x;
+        } else {
+            step_x = 1;
+            side_dist_x = ((x as i32 as f32) + 1.0 - x) * delta_dist_x;
+        }
+        if dy < 0.0 {
+            step_y = -1;
+            side_dist_y = (y - (y as i32 as f32)) * delta_dist_y;
+        } else {
+            step_y = 1;
+            side_dist_y = ((y as i32 as f32) + 1.0 - y) * delta_dist_y;
+        }
+        loop {
+            if side_dist_x < side_dist_y {
+                side_dist_x += delta_dist_x;
+                x += step_x as f32;
+                side = 0;
+            } else {
+                side_dist_y += delta_dist_y;
+                y += step_y as f32;
+                side = 1;
+            }
+            let point = Point2::new(x, y);
+            let dist = (point - start).length();
+            let cell = self.map.cell_at(x, y);
+            if let Some(hit) = &mut inside {
+                if cell == Some(hit.cell) {
+                    hit.exit = dist;
+                    continue;
+                }
+                if let Some(hit) = inside.take() {
+                    if !visit(&hit) {
+                        return;
+                    }
+                }
+            }
+            let Some((map_x, map_y)) = cell else {
+                return;
+            };
+            seen[map_x + map_y * self.map.width] = true;
+            if cell == start_cell || passed_door == cell {
+                continue;
+            }
+            let tile = self.map.tile(map_x, map_y);
+            if tile == 0 && self.map.floor_steps(map_x, map_y) == 0 {
+                continue;
+            }
+            let mut offset = if side == 1 {
+                x.rem_euclid(TILE_WIDTH) / TILE_WIDTH
+            } else {
+                y.rem_euclid(TILE_HEIGHT) / TILE_HEIGHT
+            };
+            let hit = |offset| RayHit {
+                tile,
+                cell: (map_x, map_y),
+                point,
+                side,
+                offset,
+                dist,
+                exit: dist,
+            };
+            if let Some(door) = self.door_at(map_x, map_y) {
+                passed_door = cell;
+                // The ray goes through the part of the cell the door slid out of
+                offset -= door.offset();
+                if offset >= 0.0 && !visit(&hit(offset)) {
+                    return;
+                }
+                continue;
+            }
+            inside = Some(hit(offset));
+        }
+    }
+
+    pub fn update(&mut self, controls: &Controls) {
+        if self.intermission.is_some() {
+            self.update_intermission(controls);
+            return;
+        }
+        if let Some((exit, ticks)) = self.exit {
+            if ticks == 0 {
+                self.start_intermission(exit);
+            } else {
+                self.exit = Some((exit, ticks - 1));
+            }
+            return;
+        }
+        let start = Vec2::new(self.x, self.y);
+        if controls.forward {
+            let new_x = self.theta.cos() * self.speed;
+            let new_y = self.theta.sin() * self.speed;
+            if self.can_move_to(self.x + new_x, self.y + new_y) {
+                self.x += new_x;
+                self.y += new_y;
+            }
+        }
+        if controls.backward {
+            let new_x = -self.theta.cos() * self.speed;
+            let new_y = -self.theta.sin() * self.speed;
+            if self.can_move_to(self.x + new_x, self.y + new_y) {
+                self.x += new_x;
+                self.y += new_y;
+            }
+        }
+        if controls.strafe_right {
+            let new_x = (self.theta + TAU / 4.0).cos() * self.speed;
+            let new_y = (self.theta + TAU / 4.0).sin() * self.speed;
+            if self.can_move_to(self.x + new_x, self.y + new_y) {
+                self.x += new_x;
+                self.y += new_y;
+            }
+        }
+        if controls.strafe_left {
+            let new_x = (self.theta - TAU / 4.0).cos() * self.speed;
+            let new_y = (self.theta - TAU / 4.0).sin() * self.speed;
+            if self.can_move_to(self.x + new_x, self.y + new_y) {
+                self.x += new_x;
+                self.y += new_y;
+            }
+        }
+        if controls.right {
+            self.theta += self.rotation_speed;
+        }
+        if controls.left {
+            self.theta -= self.rotation_speed;
+        }
+        self.theta += controls.mouse_dx * self.mouse_sensitivity;
+        self.theta %= TAU;
+        self.update_pitch(controls);
+        self.update_view_height(controls);
+        self.update_bob(Vec2::new(self.x, self.y).distance(start));
+        if controls.activate {
+            self.activate();
+        }
+        self.update_weapon(controls);
+        self.update_doors();
+        self.update_pushwalls();
+        self.update_actors();
+        self.pick_up_objects();
+        if self.stats.health == 0 {
+            self.die();
+            return;
+        }
+        self.ticks += 1;
+        self.tally.ticks += 1;
+        self.face.update(&mut self.rng);
+        if let Some(message) = &mut self.message {
+            message.ticks = message.ticks.saturating_sub(1);
+            if message.ticks == 0 {
+                self.message = None;
+            }
+        }
+    }
+
+    /// Use whatever is right in front of the player.
+    pub fn activate(&mut self) {
+        let x = self.x + self.theta.cos() * USE_DISTANCE;
+        let y = self.y + self.theta.sin() * USE_DISTANCE;
+        let Some((map_x, map_y)) = self.map.cell_at(x, y) else {
+            return;
+        };
+        let exit = match self.map.tile(map_x, map_y) {
+            ELEVATOR_SWITCH => Some(LevelExit::Normal),
+            SECRET_ELEVATOR_SWITCH => Some(LevelExit::Secret),
+            _ => None,
+        };
+        if let Some(exit) = exit {
+            self.exit = Some((exit, EXIT_TICKS));
+            self.play_sound(Sound::Switch, None);
+        }
+        if self.push_wall(map_x, map_y) {
+            return;
+        }
+        let keys = self.stats.keys;
+        let center = cell_center(map_x, map_y);
+        let Some(door) = self
+            .doors
+            .iter_mut()
+            .find(|door| door.x == map_x && door.y == map_y)
+        else {
+            return;
+        };
+        if keys.has(door.lock) {
+            door.toggle();
+        } else {
+            door.rattle();
+            self.message = Some(Message::new(format!(
+                "You need the {} key",
+                door.lock.name()
+            )));
+            self.play_sound(Sound::Locked, Some(center));
+        }
+    }
+
+    pub fn update_doors(&mut self) {
+        let mut occupied = vec![self.map.cell_at(self.x, self.y)];
+        occupied.extend(
+            self.actors
+                .iter()
+                .filter(|actor| actor.is_alive())
+                .map(|actor| self.map.cell_at(actor.x, actor.y)),
+        );
+        let mut sounds = vec![];
+        for door in &mut self.doors {
+            let open = door.open;
+            door.update(occupied.contains(&Some((door.x, door.y))));
+            if open <= 0.0 && door.open > 0.0 {
+                sounds.push((Sound::DoorOpen, door.x, door.y));
+            } else if open >= 1.0 && door.open < 1.0 {
+                sounds.push((Sound::DoorClose, door.x, door.y));
+            }
+        }
+        for (sound, x, y) in sounds {
+            self.play_sound(sound, Some(cell_center(x, y)));
+        }
+    }
+
+    pub fn pick_up_objects(&mut self) {
+        let position = Vec2::new(self.x, self.y);
+        let stats = &mut self.stats;
+        let tally = &mut self.tally;
+        let mut sounds = vec![];
+        self.objects.retain(|object| {
+            if (Vec2::new(object.x, object.y) - position).length() > PICKUP_DISTANCE {
+                return true;
+            }
+            match object.kind {
+                ObjectKind::GoldKey => stats.keys.gold = true,
+                ObjectKind::SilverKey => stats.keys.silver = true,
+                ObjectKind::Cross | ObjectKind::Chalice | ObjectKind::Chest | ObjectKind::Crown => {
+                    stats.score += object.kind.treasure_points().unwrap_or(0);
+                    tally.treasure += 1;
+                }
+                ObjectKind::FirstAid => {
+                    if stats.health >= 100 {
+                        return true;
+                    }
+                    stats.health = (stats.health + 25).min(100);
+                }
+                ObjectKind::AmmoClip => {
+                    if stats.ammo >= MAX_AMMO {
+                        return true;
+                    }
+                    if stats.ammo == 0 && stats.weapon == Weapon::Knife {
+                        stats.weapon = stats.best_weapon;
+                    }
+                    stats.ammo = (stats.ammo + 8).min(MAX_AMMO);
+                }
+                ObjectKind::MachineGun | ObjectKind::ChainGun => {
+                    let weapon = if object.kind == ObjectKind::MachineGun {
+                        Weapon::MachineGun
+                    } else {
+                        Weapon::ChainGun
+                    };
+                    stats.best_weapon = stats.best_weapon.max(weapon);
+                    stats.weapon = stats.best_weapon;
+                    stats.ammo = (stats.ammo + 6).min(MAX_AMMO);
+                }
+            }
+            sounds.push(match object.kind {
+                ObjectKind::GoldKey | ObjectKind::SilverKey => Sound::Key,
+                ObjectKind::Cross | ObjectKind::Chalice | ObjectKind::Chest | ObjectKind::Crown => {
+                    Sound::Treasure
+                }
+                ObjectKind::FirstAid => Sound::Health,
+                ObjectKind::AmmoClip => Sound::Ammo,
+                ObjectKind::MachineGun | ObjectKind::ChainGun => Sound::Weapon,
+            });
+            false
+        });
+        for sound in sounds {
+            self.play_sound(sound, None);
+        }
+    }
+
+    /// Hurt the player, optionally from a known position so the face can react.
+    pub fn take_damage(&mut self, amount: u32, source: Option<Point2>) {
+        if self.cheats.god {
+            return;
+        }
+        let amount = self.difficulty.damage(amount);
+        self.play_sound(Sound::PlayerPain, None);
+        self.stats.health = self.stats.health.saturating_sub(amount);
+        self.face
+            .hurt(Vec2::new(self.x, self.y), self.theta, source, amount);
+    }
+
+    /// Cast one ray per column, or per few columns at a lower render scale, and draw the walls,
+    /// returning how far they are in each column, which cells the rays went through and the
+    /// see-through walls they passed, which are left for `draw_sprites`.
+    pub fn draw_rays(&self, frame: &mut [u8]) -> (ColumnClips, Vec<bool>, Vec<MaskedSlice>) {
+        let theta_step = self.fov / WIDTH as f32;
+        let mut clips = vec![vec![]; WIDTH as usize];
+        let mut seen = vec![false; self.map.width * self.map.height];
+        let mut masked = vec![];
+        let columns = (1.0 / self.render_scale).round().max(1.0) as u32;
+        let horizon = self.horizon();
+        let eye = self.eye_z();
+        let max_top = self.map.max_top();
+        let row_of = |z: f32, dist: f32| horizon + (eye - z) * PROJECTION / dist;
+
+        for i in (0..WIDTH).step_by(columns as usize) {
+            let columns = columns.min(WIDTH - i);
+            // Rows from here down are drawn already
+            let mut clip = VIEW_HEIGHT as f32;
+            let mut column_clips = vec![];
+            self.ray_hits(
+                Vec2::new(self.x, self.y),
+                self.theta + (i as f32 - WIDTH as f32 / 2.0) * theta_step.to_radians(),
+                &mut seen,
+                |hit| {
+                    let slice = i..i + columns;
+                    if is_masked_wall(hit.tile) {
+                        // The ray goes on, hiding nothing behind
+                        masked.push(MaskedSlice {
+                            columns: slice,
+                            hit: hit.clone(),
+                            clip,
+                        });
+                        return true;
+                    }
+                    let top = self.map.solid_top(hit.cell.0, hit.cell.1);
+                    let top_row = self.draw_wall_side(frame, slice.clone(), hit, clip);
+                    clip = clip.min(top_row);
+                    if eye > top && hit.exit > hit.dist {
+                        // Looking down onto the top
+                        let far_row = row_of(top, hit.exit);
+                        let [r, g, b] = color_bytes(TOP_COLOR);
+                        let shade = self.fog_shade(hit.dist);
+                        draw_wall_slice(frame, slice, far_row..clip, shade, |_| [r, g, b, 0xff]);
+                        clip = clip.min(far_row);
+                    }
+                    clip = clip.max(0.0);
+                    column_clips.push((hit.dist, clip));
+                    // Nothing further away can reach above the lowest top something as
+                    // tall as the tallest wall on the map would have
+                    let lowest_top = if max_top > eye {
+                        row_of(max_top, hit.exit)
+                    } else {
+                        horizon
+                    };
+                    clip > 0.0 && clip > lowest_top
+                },
+            );
+            for column in &mut clips[i as usize..(i + columns) as usize] {
+                column.clone_from(&column_clips);
+            }
+        }
+        (clips, seen, masked)
+    }
+
+    /// Draw the side of the wall, door or raised floor `hit` met on `columns`, above `clip`
+    /// and blended over what is there where its texture is see-through. Returns the row its
+    /// top is on.
+    pub fn draw_wall_side(
+        &self,
+        frame: &mut [u8],
+        columns: std::ops::Range<u32>,
+        hit: &RayHit,
+        clip: f32,
+    ) -> f32 {
+        let horizon = self.horizon();
+        let eye = self.eye_z();
+        let row_of = |z: f32| horizon + (eye - z) * PROJECTION / hit.dist;
+        let top = self.map.solid_top(hit.cell.0, hit.cell.1);
+        let texture = self.wall_texture(if hit.tile == 0 {
+            STEP_TEXTURE
+        } else {
+            hit.tile
+        });
+        let column_index = ((hit.offset * 64.0) as usize).min(63);
+        let rows = row_of(top)..row_of(0.0).min(clip);
+        draw_wall_slice(
+            frame,
+            columns,
+            rows.clone(),
+            self.fog_shade(hit.dist),
+            |row| {
+                let z = eye - (row - horizon) * hit.dist / PROJECTION;
+                let texture_y = (((top - z) * 64.0) as usize).rem_euclid(64);
+                let pixel = &texture[column_index][texture_y * 4..texture_y * 4 + 4];
+                [pixel[0], pixel[1], pixel[2], pixel[3]]
+            },
+        );
+        rows.start
+    }
+
+    /// Brightness left after fog at `dist` world units, from 1 (clear) to 0.
+    pub fn fog_shade(&self, dist: f32) -> f32 {
+        if self.fog <= 0.0 {
+            return 1.0;
+        }
+        (1.0 - dist / (self.fog * TILE_WIDTH)).clamp(0.0, 1.0)
+    }
+
+    /// Write `frame` to the file the `screenshot` command asked for, if any.
+    pub fn save_screenshot(&mut self, frame: &[u8]) {
+        let Some(path) = self.screenshot.take() else {
+            return;
+        };
+        let text = match image::save_buffer(&path, frame, WIDTH, HEIGHT, image::ColorType::Rgba8) {
+            Ok(()) => format!("saved {}", path.display()),
+            Err(err) => format!("screenshot failed: {err}"),
+        };
+        self.console.print(&text);
+    }
+
+    /// Draw objects as billboards, far to near, hidden behind closer walls. See-through
+    /// walls are drawn in between, each after everything further away.
+    pub fn draw_sprites(
+        &self,
+        frame: &mut [u8],
+        clips: &[Vec<(f32, f32)>],
+        masked: &[MaskedSlice],
+    ) {
+        let position = Vec2::new(self.x, self.y);
+        let mut sprites: Vec<(f32, Point2, usize)> = self
+            .objects
+            .iter()
+            .map(|object| (Point2::new(object.x, object.y), object.kind.sprite()))
+            .chain(
+                self.actors
+                    .iter()
+                    .map(|actor| (actor.position(), actor.sprite())),
+            )
+            .map(|(point, sprite)| ((point - position).length(), point, sprite))
+            .collect();
+        sprites.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let mut masked: Vec<&MaskedSlice> = masked.iter().collect();
+        masked.sort_by(|a, b| a.hit.dist.total_cmp(&b.hit.dist));
+
+        let sheet = &self.assets.sprites;
+        for (dist, point, sprite) in sprites {
+            while let Some(slice) = masked.pop_if(|slice| slice.hit.dist > dist) {
+                self.draw_wall_side(frame, slice.columns.clone(), &slice.hit, slice.clip);
+            }
+            let to_sprite = point - position;
+            let angle = (to_sprite.y.atan2(to_sprite.x) - self.theta + PI).rem_euclid(TAU) - PI;
+            let center = WIDTH as f32 / 2.0 + angle.to_degrees() / self.fov * WIDTH as f32;
+            let size = PROJECTION / dist;
+            let left = center - size / 2.0;
+            // Standing on the floor of their cell
+            let floor = self.floor_under(point.x, point.y);
+            let top = self.horizon() + (self.eye_z() - floor - 1.0) * size;
+
+            let x_start = left.max(0.0) as usize;
+            let x_end = (left + size).min(WIDTH as f32).max(0.0) as usize;
+            let y_start = top.max(0.0) as usize;
+            let shade = self.fog_shade(dist);
+            for (i, column) in clips.iter().enumerate().take(x_end).skip(x_start) {
+                // Hidden below the top of the nearest wall in front
+                let clip = column
+                    .iter()
+                    .take_while(|(depth, _)| *depth < dist)
+                    .last()
+                    .map_or(VIEW_HEIGHT as f32, |(_, clip)| *clip);
+                let y_end = (top + size).min(clip).max(0.0) as usize;
+                let texture_x = (((i as f32 - left) / size) * sheet.frame_width as f32) as usize;
+                for j in y_start..y_end {
+                    let texture_y =
+                        (((j as f32 - top) / size) * sheet.frame_height as f32) as usize;
+                    let [r, g, b, a] = sheet.pixel(
+                        sprite,
+                        texture_x.min(sheet.frame_width - 1),
+                        texture_y.min(sheet.frame_height - 1),
+                    );
+                    if a > 0 {
+                        let color =
+                            Srgb::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0) * shade;
+                        draw_pixel_raw(frame, i as f32, j as f32, color);
+                    }
+                }
+            }
+        }
+        for slice in masked.into_iter().rev() {
+            self.draw_wall_side(frame, slice.columns.clone(), &slice.hit, slice.clip);
+        }
+    }
+
+    /// Render the view, status bar and any menu or console into `frame`.
+    pub fn draw(&mut self, frame: &mut [u8]) {
+        clear_frame(frame, Srgb::new(0.0, 0.0, 0.0));
+        if self.intermission.is_some() {
+            self.draw_intermission(frame);
+            self.draw_status_bar(frame);
+            self.draw_menu(frame);
+            self.save_screenshot(frame);
+            self.draw_console(frame);
+            return;
+        }
+        let (clips, seen, masked) = self.draw_rays(frame);
+        self.reveal(&seen);
+        self.draw_sprites(frame, &clips, &masked);
+        self.draw_weapon(frame);
+        self.draw_automap(frame);
+        self.draw_message(frame);
+        self.draw_status_bar(frame);
+        self.draw_menu(frame);
+        self.save_screenshot(frame);
+        self.draw_console(frame);
+    }
+}
+
+/// The 8-bit channels of `color`.
+fn color_bytes(color: Srgb) -> [u8; 3] {
+    let color: Srgb<u8> = color.into_format();
+    [color.red, color.green, color.blue]
+}
+
+/// Fill `columns` of the view between the fractional `rows` with the RGBA color `texel`
+/// gives for each row, darkened to `shade` and blended over the frame by its alpha.
+fn draw_wall_slice(
+    frame: &mut [u8],
+    columns: std::ops::Range<u32>,
+    rows: std::ops::Range<f32>,
+    shade: f32,
+    texel: impl Fn(f32) -> [u8; 4],
+) {
+    let start = rows.start.max(0.0).round() as u32;
+    let end = rows.end.min(VIEW_HEIGHT as f32).round().max(0.0) as u32;
+    for row in start..end {
+        let [r, g, b, a] = texel(row as f32 + 0.5);
+        if a == 0 {
+            continue;
+        }
+        let color = [r, g, b].map(|channel| channel as f32 * shade);
+        let alpha = a as f32 / 255.0;
+        for column in columns.clone() {
+            let index = ((row * WIDTH + column) * 4) as usize;
+            for (channel, &value) in frame[index..index + 3].iter_mut().zip(&color) {
+                *channel = (value * alpha + *channel as f32 * (1.0 - alpha)) as u8;
+            }
+            frame[index + 3] = 0xff;
+        }
+    }
+}
@@ -2,49 +2,33 @@
 #![forbid(unsafe_code)]
 #![allow(dead_code, unused_assignments)]
 
-use std::f32::consts::{PI, TAU};
 use std::sync::Arc;
 use std::time::Duration;
 
+use clap::Parser;
 use egui::mutex::Mutex;
 use error_iter::ErrorIter as _;
 use fps_ticker::Fps;
 use game_loop::{game_loop, Time, TimeTrait};
-use glam::Vec2;
-
-use log::error;
-use palette::Srgb;
+use log::{error, info};
 use pixels::{Error, Pixels, SurfaceTexture};
 use winit::dpi::LogicalSize;
 use winit::event::{Event, VirtualKeyCode};
 use winit::event_loop::EventLoop;
-use winit::window::WindowBuilder;
-use winit_input_helper::WinitInputHelper;
+use winit::window::{Fullscreen, WindowBuilder};
+use winit_input_helper::{TextChar, WinitInputHelper};
+
+use wolfenstein_rs::{
+    assets::*, cheats::*, console::*, demo::*, player::*, save::*, Controls, World, HEIGHT,
+    TICKS_PER_SECOND, WIDTH,
+};
 
-mod geo;
-mod graphics;
+mod cli;
+mod editor;
 mod gui;
-mod helpers;
-mod map;
 
-use geo::*;
-use graphics::*;
+use cli::*;
 use gui::*;
-use helpers::*;
-use map::*;
-
-const WIDTH: u32 = 1920 / 3;
-const HEIGHT: u32 = 1080 / 3;
-
-#[derive(Debug, Default)]
-struct Controls {
-    pub forward: bool,
-    pub backward: bool,
-    pub left: bool,
-    pub right: bool,
-    pub strafe_left: bool,
-    pub strafe_right: bool,
-}
 
 struct Game {
     pub pixels: Pixels,
@@ -76,43 +60,77 @@ impl Game {
 }
 
 fn main() -> Result<(), Error> {
-    env_logger::init();
+    let args = Args::parse();
+    let mut logger = env_logger::Builder::from_default_env();
+    if let Some(level) = args.log_level {
+        logger.filter_level(level);
+    }
+    logger.init();
+
+    let world = Arc::new(Mutex::new(World::new(Assets::load(args.assets.as_deref()))));
+    world.lock().load_options();
+    match world.lock().exec(&autoexec_path()) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+            error!("autoexec failed: {err}")
+        }
+        _ => {}
+    }
+    args.start(&mut world.lock());
+
+    if let Some(path) = &args.screenshot {
+        let mut world = world.lock();
+        while matches!(world.demo, DemoState::Playing(..)) {
+            world.tick(&Controls::default());
+        }
+        for _ in 0..args.ticks {
+            world.tick(&Controls::default());
+        }
+        let mut frame = vec![0; (WIDTH * HEIGHT * 4) as usize];
+        world.draw(&mut frame);
+        if let Err(err) = image::save_buffer(path, &frame, WIDTH, HEIGHT, image::ColorType::Rgba8) {
+            error!("can't write {}: {err}", path.display());
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let event_loop = EventLoop::new();
     let window = {
-        let size = LogicalSize::new((WIDTH * 2) as f64, (HEIGHT * 2) as f64);
+        let min_size = LogicalSize::new((WIDTH * 2) as f64, (HEIGHT * 2) as f64);
+        let (size, min_size) = match args.window_size {
+            Some((width, height)) => (
+                LogicalSize::new(width as f64, height as f64),
+                LogicalSize::new(WIDTH as f64, HEIGHT as f64),
+            ),
+            None => (min_size, min_size),
+        };
         WindowBuilder::new()
             .with_title("wolfenstein-rs")
             .with_inner_size(size)
-            .with_min_inner_size(size)
+            .with_min_inner_size(min_size)
+            .with_fullscreen(args.fullscreen.then_some(Fullscreen::Borderless(None)))
             .build(&event_loop)
             .unwrap()
     };
 
     let fps = Arc::new(Mutex::new(Fps::default()));
 
-    let mut textures: Vec<Vec<Vec<u8>>> = vec![];
-    let mut r = std::io::Cursor::new(include_bytes!("../assets/textures.png"));
-    let img = image::load(&mut r, image::ImageFormat::Png).unwrap();
-    for i in 0..=7 {
-        let img = img.crop_imm(i * (img.width() / 8), 0, img.width() / 8, img.height());
-        let mut vertical_chunks = vec![];
-        for i in 0..img.width() {
-            let chunk = img.crop_imm(i, 0, 1, img.height()).to_rgba8().to_vec();
-            vertical_chunks.push(chunk);
+    // Has to stay alive for as long as sound should play
+    let _stream = match &args.wav {
+        Some(path) => {
+            if let Err(err) = world.lock().audio.record_wav(path) {
+                error!("can't write {}: {err}", path.display());
+            }
+            None
         }
-        textures.push(vertical_chunks);
-    }
-
-    // Write the textures to disk for debugging
-    // for (i, texture) in textures.iter().enumerate() {
-    //     for (j, texture) in texture.iter().enumerate() {
-    //         texture
-    //             .save(format!("textures/texture_{}_{}.png", i, j))
-    //             .unwrap();
-    //     }
-    // }
-
-    let world = Arc::new(Mutex::new(World::new(textures.clone())));
+        None => match world.lock().audio.open_device() {
+            Ok(stream) => Some(stream),
+            Err(err) => {
+                info!("playing without sound: {err}");
+                None
+            }
+        },
+    };
 
     let (pixels, framework) = {
         let window_size = window.inner_size();
@@ -133,15 +151,30 @@ fn main() -> Result<(), Error> {
     };
 
     let game = Game::new(pixels, framework, fps.clone(), world.clone());
+    let record_demo = args.record_demo;
     game_loop(
         event_loop,
         window,
         game,
-        60,
+        TICKS_PER_SECOND,
         0.1,
         |g| {
             // Update
-            g.game.world.lock().update(&g.game.controls);
+            {
+                let mut world = g.game.world.lock();
+                if g.game.controls.quick_save {
+                    world.save_to_slot(QUICK_SAVE_SLOT);
+                }
+                if g.game.controls.quick_load {
+                    world.load_from_slot(QUICK_SAVE_SLOT);
+                }
+                // The game is paused while the console or a menu is open
+                if !g.game.paused && !world.console.open {
+                    world.tick(&g.game.controls);
+                }
+                world.write_changed_options();
+            }
+            g.game.controls.consume_actions();
         },
         |g| {
             // Draw
@@ -179,13 +212,124 @@ fn main() -> Result<(), Error> {
                 std::thread::sleep(Duration::from_secs_f64(dt));
             }
         },
-        |g, event| {
+        move |g, event| {
             // Handle events
-            if g.game.input.update(&event) {
-                // Close events
-                if g.game.input.key_pressed(VirtualKeyCode::Escape)
-                    || g.game.input.close_requested()
+            if g.game.input.update(event) {
+                let input = &g.game.input;
+                // The console takes all keys while it is open
+                let console_was_open = g.game.world.lock().console.open;
                 {
+                    let mut world = g.game.world.lock();
+                    if input.key_pressed(VirtualKeyCode::Grave) {
+                        world.console.toggle();
+                    } else if world.console.open {
+                        for text in input.text() {
+                            match text {
+                                TextChar::Char(c) => world.console.type_char(c),
+                                TextChar::Back => world.console.backspace(),
+                            }
+                        }
+                        if input.key_pressed(VirtualKeyCode::Return) {
+                            world.submit_console();
+                        }
+                        if input.key_pressed(VirtualKeyCode::Tab) {
+                            world.console.complete();
+                        }
+                        if input.key_pressed(VirtualKeyCode::Up) {
+                            world.console.history_up();
+                        }
+                        if input.key_pressed(VirtualKeyCode::Down) {
+                            world.console.history_down();
+                        }
+                        if input.key_pressed(VirtualKeyCode::Escape) {
+                            world.console.open = false;
+                        }
+                    } else if world.menu.is_open() {
+                        if input.key_pressed(VirtualKeyCode::Up)
+                            || input.key_pressed(VirtualKeyCode::W)
+                        {
+                            world.menu_move(-1);
+                        }
+                        if input.key_pressed(VirtualKeyCode::Down)
+                            || input.key_pressed(VirtualKeyCode::S)
+                        {
+                            world.menu_move(1);
+                        }
+                        if input.key_pressed(VirtualKeyCode::Left)
+                            || input.key_pressed(VirtualKeyCode::A)
+                        {
+                            world.menu_adjust(-1.0);
+                        }
+                        if input.key_pressed(VirtualKeyCode::Right)
+                            || input.key_pressed(VirtualKeyCode::D)
+                        {
+                            world.menu_adjust(1.0);
+                        }
+                        if input.key_pressed(VirtualKeyCode::Return)
+                            || input.key_pressed(VirtualKeyCode::Space)
+                        {
+                            world.menu_select();
+                        }
+                        if input.key_pressed(VirtualKeyCode::Escape) {
+                            world.menu.back();
+                        }
+                        let mouse = input
+                            .mouse()
+                            .and_then(|position| g.game.pixels.window_pos_to_pixel(position).ok());
+                        if let Some((x, y)) = mouse {
+                            if input.mouse_diff() != (0.0, 0.0) {
+                                world.menu_hover(x as f32, y as f32);
+                            }
+                            if input.mouse_pressed(0) {
+                                world.menu_click(x as f32, y as f32);
+                            }
+                        }
+                    } else {
+                        if input.key_pressed(VirtualKeyCode::Escape) {
+                            world.open_menu();
+                        }
+                        if input.key_pressed(VirtualKeyCode::Tab) {
+                            world.automap.mode = world.automap.mode.next();
+                        }
+                        if input.key_pressed(VirtualKeyCode::Equals)
+                            || input.key_pressed(VirtualKeyCode::NumpadAdd)
+                        {
+                            world.automap.zoom_in();
+                        }
+                        if input.key_pressed(VirtualKeyCode::Minus)
+                            || input.key_pressed(VirtualKeyCode::NumpadSubtract)
+                        {
+                            world.automap.zoom_out();
+                        }
+                        if input.key_pressed(VirtualKeyCode::R) {
+                            world.automap.rotate = !world.automap.rotate;
+                        }
+                        // M-L-I like the original, the other cheats with Backspace held
+                        let mli = [VirtualKeyCode::M, VirtualKeyCode::L, VirtualKeyCode::I];
+                        if mli.iter().all(|&key| input.key_held(key))
+                            && mli.iter().any(|&key| input.key_pressed(key))
+                        {
+                            world.give_all();
+                        }
+                        if input.key_held(VirtualKeyCode::Back) {
+                            for (key, cheat) in [
+                                (VirtualKeyCode::N, Cheat::Noclip),
+                                (VirtualKeyCode::G, Cheat::God),
+                                (VirtualKeyCode::M, Cheat::RevealMap),
+                                (VirtualKeyCode::F, Cheat::FreezeAi),
+                            ] {
+                                if input.key_pressed(key) {
+                                    world.toggle_cheat(cheat);
+                                }
+                            }
+                        }
+                    }
+                }
+                // Close events
+                if g.game.world.lock().menu.quit || g.game.input.close_requested() {
+                    if let Some(path) = &record_demo {
+                        g.game.world.lock().stop_recording(path);
+                    }
                     g.exit();
                 }
                 g.game.controls = {
@@ -208,8 +352,53 @@ fn main() -> Result<(), Error> {
                     if g.game.input.key_held(VirtualKeyCode::E) {
                         controls.right = true;
                     }
+                    if g.game.input.key_held(VirtualKeyCode::LControl)
+                        || g.game.input.key_held(VirtualKeyCode::RControl)
+                    {
+                        controls.fire = true;
+                    }
+                    if g.game.input.key_held(VirtualKeyCode::C) {
+                        controls.crouch = true;
+                    }
+                    if g.game.input.key_held(VirtualKeyCode::PageUp) {
+                        controls.look_up = true;
+                    }
+                    if g.game.input.key_held(VirtualKeyCode::PageDown) {
+                        controls.look_down = true;
+                    }
+                    let weapon_keys = [
+                        VirtualKeyCode::Key1,
+                        VirtualKeyCode::Key2,
+                        VirtualKeyCode::Key3,
+                        VirtualKeyCode::Key4,
+                    ];
+                    controls.weapon = g.game.controls.weapon.or_else(|| {
+                        (1..)
+                            .zip(weapon_keys)
+                            .find(|&(_, key)| g.game.input.key_pressed(key))
+                            .and_then(|(number, _)| Weapon::from_number(number))
+                    });
+                    // Keep presses an update hasn't seen yet
+                    controls.activate =
+                        g.game.controls.activate || g.game.input.key_pressed(VirtualKeyCode::Space);
+                    // Turn and look with the mouse while the right button is held
+                    controls.mouse_dx = g.game.controls.mouse_dx;
+                    controls.mouse_dy = g.game.controls.mouse_dy;
+                    if g.game.input.mouse_held(1) {
+                        let (dx, dy) = g.game.input.mouse_diff();
+                        controls.mouse_dx += dx;
+                        controls.mouse_dy += dy;
+                    }
+                    controls.quick_save =
+                        g.game.controls.quick_save || g.game.input.key_pressed(VirtualKeyCode::F5);
+                    controls.quick_load =
+                        g.game.controls.quick_load || g.game.input.key_pressed(VirtualKeyCode::F9);
                     controls
                 };
+                g.game.paused = g.game.world.lock().menu.is_open();
+                if console_was_open || g.game.paused {
+                    g.game.controls = Controls::default();
+                }
 
                 // Update the scale factor
                 if let Some(scale_factor) = g.game.input.scale_factor() {
@@ -226,12 +415,9 @@ fn main() -> Result<(), Error> {
                 // }
             }
 
-            match event {
-                Event::WindowEvent { event, .. } => {
-                    // Update egui inputs
-                    g.game.framework.handle_event(&event);
-                }
-                _ => (),
+            if let Event::WindowEvent { event, .. } = event {
+                // Update egui inputs
+                g.game.framework.handle_event(event);
             }
         },
     );
@@ -243,220 +429,3 @@ fn log_error<E: std::error::Error + 'static>(method_name: &str, err: E) {
         error!("  Caused by: {source}");
     }
 }
-
-struct World {
-    speed: f32,
-    rotation_speed: f32,
-    x: f32,
-    y: f32,
-    theta: f32,
-    fov: f32,
-    textures: Vec<Vec<Vec<u8>>>,
-}
-
-impl World {
-    fn new(textures: Vec<Vec<Vec<u8>>>) -> Self {
-        Self {
-            x: WIDTH as f32 / 2.0,
-            y: HEIGHT as f32 / 2.0,
-            theta: -PI / 2.0,
-            speed: 2.0,
-            rotation_speed: 0.05,
-            fov: 60.0,
-            textures,
-        }
-    }
-
-    fn will_hit_obstacle(&self, x: f32, y: f32) -> Option<u8> {
-        let map_x = (x / (WIDTH as f32 / MAP_WIDTH as f32)).floor() as usize;
-        let map_y = (y / (HEIGHT as f32 / MAP_HEIGHT as f32)).floor() as usize;
-        if map_x >= MAP_WIDTH || map_y >= MAP_HEIGHT {
-            return Some(0);
-        }
-        if MAP[map_y][map_x] != 0 {
-            return Some(MAP[map_y][map_x]);
-        } else {
-            return None;
-        }
-    }
-
-    fn ray_hits(&self, start: Point2, angle: f32) -> Option<(Vec<Vec<u8>>, Point2, u32)> {
-        let mut x = start.x;
-        let mut y = start.y;
-        let dx = angle.cos();
-        let dy = angle.sin();
-        let mut step_x = 1;
-        let mut step_y = 1;
-        let mut side = 0;
-        let mut side_dist_x = 0.0;
-        let mut side_dist_y = 0.0;
-        let delta_dist_x = (1.0 / dx).abs();
-        let delta_dist_y = (1.0 / dy).abs();
-        let mut hit = None;
-        if dx < 0.0 {
-            step_x = -1;
-            side_dist_x = (x - (x as i32 as f32)) * delta_dist_x;
-        } else {
-            step_x = 1;
-            side_dist_x = ((x as i32 as f32) + 1.0 - x) * delta_dist_x;
-        }
-        if dy < 0.0 {
-            step_y = -1;
-            side_dist_y = (y - (y as i32 as f32)) * delta_dist_y;
-        } else {
-            step_y = 1;
-            side_dist_y = ((y as i32 as f32) + 1.0 - y) * delta_dist_y;
-        }
-        while hit.is_none() {
-            if side_dist_x < side_dist_y {
-                side_dist_x += delta_dist_x;
-                x += step_x as f32;
-                side = 0;
-            } else {
-                side_dist_y += delta_dist_y;
-                y += step_y as f32;
-                side = 1;
-            }
-            let map_x = (x / (WIDTH as f32 / MAP_WIDTH as f32)).floor() as usize;
-            let map_y = (y / (HEIGHT as f32 / MAP_HEIGHT as f32)).floor() as usize;
-            if map_x >= MAP_WIDTH || map_y >= MAP_HEIGHT {
-                return None;
-            }
-            if MAP[map_y][map_x] != 0 {
-                let texture = self.textures[MAP[map_y][map_x] as usize - 1].clone();
-                hit = Some((texture, Point2::new(x, y), side));
-            }
-        }
-        hit
-    }
-
-    fn update(&mut self, controls: &Controls) {
-        if controls.forward {
-            let new_x = self.theta.cos() * self.speed;
-            let new_y = self.theta.sin() * self.speed;
-            if self
-                .will_hit_obstacle(self.x + new_x, self.y + new_y)
-                .is_none()
-            {
-                self.x += new_x;
-                self.y += new_y;
-            }
-        }
-        if controls.backward {
-            let new_x = -self.theta.cos() * self.speed;
-            let new_y = -self.theta.sin() * self.speed;
-            if self
-                .will_hit_obstacle(self.x + new_x, self.y + new_y)
-                .is_none()
-            {
-                self.x += new_x;
-                self.y += new_y;
-            }
-        }
-        if controls.strafe_right {
-            let new_x = (self.theta + TAU / 4.0).cos() * self.speed;
-            let new_y = (self.theta + TAU / 4.0).sin() * self.speed;
-            if self
-                .will_hit_obstacle(self.x + new_x, self.y + new_y)
-                .is_none()
-            {
-                self.x += new_x;
-                self.y += new_y;
-            }
-        }
-        if controls.strafe_left {
-            let new_x = (self.theta - TAU / 4.0).cos() * self.speed;
-            let new_y = (self.theta - TAU / 4.0).sin() * self.speed;
-            if self
-                .will_hit_obstacle(self.x + new_x, self.y + new_y)
-                .is_none()
-            {
-                self.x += new_x;
-                self.y += new_y;
-            }
-        }
-        if controls.right {
-            self.theta += self.rotation_speed;
-        }
-        if controls.left {
-            self.theta -= self.rotation_speed;
-        }
-        self.theta = self.theta % TAU;
-    }
-
-    fn draw_minimap(&self, frame: &mut [u8]) {
-        let cell_size_x = WIDTH as f32 / MAP_WIDTH as f32;
-        let cell_size_y = HEIGHT as f32 / MAP_HEIGHT as f32;
-        for i in 0..MAP_HEIGHT {
-            for j in 0..MAP_WIDTH {
-                let color = match MAP[i][j] {
-                    0 => Srgb::new(0.0, 0.0, 0.0),
-                    1 => Srgb::new(0.0, 0.0, 1.0),
-                    2 => Srgb::new(0.0, 1.0, 0.0),
-                    3 => Srgb::new(1.0, 0.0, 0.0),
-                    4 => Srgb::new(1.0, 1.0, 0.0),
-                    5 => Srgb::new(1.0, 0.0, 1.0),
-                    _ => Srgb::new(1.0, 1.0, 1.0),
-                };
-
-                ColorRect::new(
-                    j as f32 * cell_size_x,
-                    i as f32 * cell_size_y,
-                    cell_size_x,
-                    cell_size_y,
-                    color,
-                )
-                .draw(frame);
-            }
-        }
-    }
-
-    fn draw_player(&self, frame: &mut [u8]) {
-        ColorRect::new(
-            self.x - 2.0,
-            self.y - 2.0,
-            4.0,
-            4.0,
-            Srgb::new(1.0, 1.0, 1.0),
-        )
-        .draw(frame);
-    }
-
-    fn draw_rays(&self, frame: &mut [u8]) {
-        let theta_step = self.fov / WIDTH as f32;
-        let tile_width = WIDTH as f32 / MAP_WIDTH as f32;
-        let tile_ratio = 64.0 / tile_width;
-
-        for i in 0..WIDTH {
-            if let Some((columns, hit, side)) = self.ray_hits(
-                Vec2::new(self.x, self.y),
-                self.theta + (i as f32 - WIDTH as f32 / 2.0) * theta_step.to_radians(),
-            ) {
-                let dist = (Vec2::new(self.x, self.y) - hit).length();
-                let height = HEIGHT as f32 / dist * 50.0;
-
-                let tile_relative_x = if side == 1 { hit.x } else { hit.y } % tile_width;
-                let column_index = ((tile_relative_x * tile_ratio) as usize).min(63);
-
-                let texture = &columns[column_index];
-                TextureRect::new(
-                    i as f32,
-                    (HEIGHT as f32 - height) / 2.0,
-                    1.0,
-                    height,
-                    texture.to_vec(),
-                    1,
-                    64,
-                )
-                .draw(frame);
-            }
-        }
-    }
-
-    fn draw(&self, frame: &mut [u8]) {
-        clear_frame(frame, Srgb::new(0.0, 0.0, 0.0));
-        self.draw_rays(frame);
-        // self.draw_minimap(frame);
-        // self.draw_player(frame);
-    }
-}
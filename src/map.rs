@@ -1,152 +1,487 @@
+use crate::{geo::Point2, HEIGHT, WIDTH};
+
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::fmt;
+
+/// Size of the original built-in map, which the tile size is derived from.
 pub const MAP_WIDTH: usize = 24;
 pub const MAP_HEIGHT: usize = 24;
 
-pub const MAP: [[u8; MAP_WIDTH]; MAP_HEIGHT] = [
-    [
-        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
-    ],
-    [
-        1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-    ],
-    [
-        1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-    ],
-    [
-        1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-    ],
-    [
-        1, 0, 0, 0, 0, 0, 2, 2, 2, 2, 2, 0, 0, 0, 0, 3, 0, 3, 0, 3, 0, 0, 0, 1,
-    ],
-    [
-        1, 0, 0, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-    ],
-    [
-        1, 0, 0, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 0, 3, 0, 0, 0, 3, 0, 0, 0, 1,
-    ],
-    [
-        1, 0, 0, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-    ],
-    [
-        1, 0, 0, 0, 0, 0, 2, 2, 0, 2, 2, 0, 0, 0, 0, 3, 0, 3, 0, 3, 0, 0, 0, 1,
-    ],
-    [
-        1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-    ],
-    [
-        1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-    ],
-    [
-        1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-    ],
-    [
-        1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-    ],
-    [
-        1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-    ],
-    [
-        1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-    ],
-    [
-        1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-    ],
-    [
-        1, 4, 4, 4, 4, 4, 4, 4, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-    ],
-    [
-        1, 4, 0, 4, 0, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-    ],
-    [
-        1, 4, 0, 0, 0, 0, 5, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-    ],
-    [
-        1, 4, 0, 4, 0, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-    ],
-    [
-        1, 4, 0, 4, 4, 4, 4, 4, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-    ],
-    [
-        1, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-    ],
-    [
-        1, 4, 4, 4, 4, 4, 4, 4, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-    ],
-    [
-        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
-    ],
-];
-
-pub const MAP_1: [[u8; MAP_WIDTH]; MAP_HEIGHT] = [
-    [
-        4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 7, 7, 7, 7, 7, 7, 7, 7,
-    ],
-    [
-        4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 0, 0, 0, 7,
-    ],
-    [
-        4, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 7,
-    ],
-    [
-        4, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 7,
-    ],
-    [
-        4, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0, 7,
-    ],
-    [
-        4, 0, 4, 0, 0, 0, 0, 5, 5, 5, 5, 5, 5, 5, 5, 7, 7, 0, 7, 7, 7, 7, 7, 7,
-    ],
-    [
-        4, 0, 5, 0, 0, 0, 0, 5, 0, 5, 0, 5, 0, 5, 0, 5, 7, 0, 0, 0, 7, 7, 7, 1,
-    ],
-    [
-        4, 0, 6, 0, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0, 5, 7, 0, 0, 0, 0, 0, 0, 8,
-    ],
-    [
-        4, 0, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 7, 7, 7, 7, 1,
-    ],
-    [
-        4, 0, 8, 0, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0, 5, 7, 0, 0, 0, 0, 0, 0, 8,
-    ],
-    [
-        4, 0, 0, 0, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0, 5, 7, 0, 0, 0, 7, 7, 7, 1,
-    ],
-    [
-        4, 0, 0, 0, 0, 0, 0, 5, 5, 5, 5, 0, 5, 5, 5, 5, 7, 7, 7, 7, 7, 7, 7, 1,
-    ],
-    [
-        6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 0, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6,
-    ],
-    [
-        8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4,
-    ],
-    [
-        6, 6, 6, 6, 6, 6, 0, 6, 6, 6, 6, 0, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6,
-    ],
-    [
-        4, 4, 4, 4, 4, 4, 0, 4, 4, 4, 6, 0, 6, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3,
-    ],
-    [
-        4, 0, 0, 0, 0, 0, 0, 0, 0, 4, 6, 0, 6, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 2,
-    ],
-    [
-        4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 6, 2, 0, 0, 5, 0, 0, 2, 0, 0, 0, 2, 2,
-    ],
-    [
-        4, 0, 0, 0, 0, 0, 0, 0, 0, 4, 6, 0, 6, 2, 0, 0, 0, 0, 2, 2, 0, 2, 2, 2,
-    ],
-    [
-        4, 0, 6, 0, 6, 0, 0, 0, 0, 4, 6, 0, 0, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 2,
-    ],
-    [
-        4, 0, 0, 5, 0, 0, 0, 0, 0, 4, 6, 0, 6, 2, 0, 0, 0, 0, 2, 2, 0, 2, 2, 2,
-    ],
-    [
-        4, 0, 6, 0, 6, 0, 0, 0, 0, 4, 6, 0, 6, 2, 0, 0, 5, 0, 0, 2, 0, 0, 0, 2,
-    ],
-    [
-        4, 0, 0, 0, 0, 0, 0, 0, 0, 4, 6, 0, 6, 2, 0, 0, 0, 0, 2, 0, 0, 0, 2, 2,
-    ],
-    [
-        4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 1, 1, 1, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3,
-    ],
-];
+/// Largest width or height a map file may have.
+pub const MAX_MAP_SIZE: usize = 128;
+
+/// Size of a map cell in world units.
+pub const TILE_WIDTH: f32 = WIDTH as f32 / MAP_WIDTH as f32;
+pub const TILE_HEIGHT: f32 = HEIGHT as f32 / MAP_HEIGHT as f32;
+
+/// Steps wall and floor heights are given in, per cell of height.
+pub const HEIGHT_STEPS: u8 = 8;
+/// Highest floor the player can step up onto from the one they stand on, in height steps.
+pub const MAX_STEP: u8 = HEIGHT_STEPS / 2;
+/// Highest value of the `heights` and `floors` planes, four cells.
+pub const MAX_HEIGHT_STEPS: u8 = 4 * HEIGHT_STEPS;
+
+/// Middle of cell `x`, `y` in world coordinates.
+pub fn cell_center(x: usize, y: usize) -> Point2 {
+    Point2::new(
+        (x as f32 + 0.5) * TILE_WIDTH,
+        (y as f32 + 0.5) * TILE_HEIGHT,
+    )
+}
+
+/// Number of wall textures in `assets/textures.png`, numbered from 1 in the wall plane.
+pub const WALL_TEXTURES: u8 = 8;
+/// First of the see-through walls, cages, windows and fences, which take their textures
+/// from `masked.png`.
+pub const FIRST_MASKED_WALL: u8 = 11;
+pub const LAST_MASKED_WALL: u8 = 13;
+// Wall plane values above the wall textures, numbered like the original map editor.
+pub const ELEVATOR_SWITCH: u8 = 21;
+pub const SECRET_ELEVATOR_SWITCH: u8 = 22;
+pub const DOOR: u8 = 90;
+pub const GOLD_DOOR: u8 = 92;
+pub const SILVER_DOOR: u8 = 94;
+
+// Object plane values.
+pub const PLAYER_START_NORTH: u8 = 19;
+pub const PLAYER_START_EAST: u8 = 20;
+pub const PLAYER_START_SOUTH: u8 = 21;
+pub const PLAYER_START_WEST: u8 = 22;
+pub const GOLD_KEY: u8 = 43;
+pub const SILVER_KEY: u8 = 44;
+pub const FIRST_AID: u8 = 48;
+pub const AMMO_CLIP: u8 = 49;
+pub const MACHINE_GUN: u8 = 50;
+pub const CHAIN_GUN: u8 = 51;
+pub const CROSS: u8 = 52;
+pub const CHALICE: u8 = 53;
+pub const CHEST: u8 = 54;
+pub const CROWN: u8 = 55;
+/// Marks the wall tile above it as a secret push wall.
+pub const PUSHWALL: u8 = 98;
+pub const GUARD_EAST: u8 = 108;
+pub const GUARD_NORTH: u8 = 109;
+pub const GUARD_WEST: u8 = 110;
+pub const GUARD_SOUTH: u8 = 111;
+/// Guards placed from medium skill on use the easy values plus this, from hard skill on plus twice this.
+pub const GUARD_TIER_OFFSET: u8 = 36;
+
+pub fn is_door(tile: u8) -> bool {
+    matches!(tile, DOOR | GOLD_DOOR | SILVER_DOOR)
+}
+
+pub fn is_elevator_switch(tile: u8) -> bool {
+    matches!(tile, ELEVATOR_SWITCH | SECRET_ELEVATOR_SWITCH)
+}
+
+/// Whether `tile` is a wall that can be seen through where its texture is transparent.
+pub fn is_masked_wall(tile: u8) -> bool {
+    (FIRST_MASKED_WALL..=LAST_MASKED_WALL).contains(&tile)
+}
+
+/// Whether `tile` is a wall plane value the game knows how to draw: a wall texture, a
+/// see-through wall, an elevator switch or a door.
+pub fn is_known_wall(tile: u8) -> bool {
+    tile <= WALL_TEXTURES || is_masked_wall(tile) || is_elevator_switch(tile) || is_door(tile)
+}
+
+/// Whether `object` is an object plane value the game knows how to place.
+pub fn is_known_object(object: u8) -> bool {
+    matches!(
+        object,
+        0 | PLAYER_START_NORTH..=PLAYER_START_WEST
+            | GOLD_KEY
+            | SILVER_KEY
+            | FIRST_AID..=CROWN
+            | PUSHWALL
+    ) || guard_tier(object).is_some()
+}
+
+/// Tier of a guard object: 0 on every skill, 1 from medium on and 2 on hard only.
+pub fn guard_tier(object: u8) -> Option<u8> {
+    (0..3).find(|tier| {
+        let offset = tier * GUARD_TIER_OFFSET;
+        (GUARD_EAST + offset..=GUARD_SOUTH + offset).contains(&object)
+    })
+}
+
+#[derive(Debug)]
+pub enum MapError {
+    MissingSize,
+    MissingPlane(&'static str),
+    UnknownLine(usize),
+    InvalidNumber(usize),
+    WrongRowLength(usize),
+    TooLarge(usize),
+    UnknownWall(usize, u8),
+    UnknownObject(usize, u8),
+    TooHigh(usize, u8),
+    RepeatedSize(usize),
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapError::MissingSize => write!(f, "map has no size line before its planes"),
+            MapError::MissingPlane(plane) => write!(f, "map is missing rows of the {plane} plane"),
+            MapError::UnknownLine(line) => write!(f, "line {line}: unknown map entry"),
+            MapError::InvalidNumber(line) => write!(f, "line {line}: invalid number"),
+            MapError::WrongRowLength(line) => write!(f, "line {line}: row has the wrong length"),
+            MapError::TooLarge(line) => {
+                write!(
+                    f,
+                    "line {line}: map is larger than {MAX_MAP_SIZE}x{MAX_MAP_SIZE}"
+                )
+            }
+            MapError::UnknownWall(line, tile) => write!(f, "line {line}: unknown wall {tile}"),
+            MapError::UnknownObject(line, object) => {
+                write!(f, "line {line}: unknown object {object}")
+            }
+            MapError::TooHigh(line, steps) => write!(
+                f,
+                "line {line}: height {steps} is above the highest of {MAX_HEIGHT_STEPS}"
+            ),
+            MapError::RepeatedSize(line) => write!(f, "line {line}: map already has a size"),
+        }
+    }
+}
+
+impl std::error::Error for MapError {}
+
+/// A level: one plane of wall tiles and one plane of objects, stored row by row, and
+/// optionally the height of each wall and of the floor under each cell.
+///
+/// On disk a map is a text file: `name`, `par` and `size` header lines, then a `walls`
+/// and an `objects` line each followed by one line of numbers per row. `heights` and
+/// `floors` planes can follow in the same way, in eighths of a cell, a wall height of 0
+/// meaning a wall one cell tall, and neither above `MAX_HEIGHT_STEPS`.
+/// Blank lines and lines starting with `#` are ignored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Map {
+    pub name: String,
+    /// Par time in seconds.
+    pub par: u32,
+    pub width: usize,
+    pub height: usize,
+    pub walls: Vec<u8>,
+    pub objects: Vec<u8>,
+    /// Height of the wall in each cell, in `HEIGHT_STEPS` per cell, 0 for one cell.
+    #[serde(default)]
+    pub heights: Vec<u8>,
+    /// Height of the floor of each cell, in `HEIGHT_STEPS` per cell. Walls stand on it.
+    #[serde(default)]
+    pub floors: Vec<u8>,
+}
+
+impl Map {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            name: String::new(),
+            par: 0,
+            width,
+            height,
+            walls: vec![0; width * height],
+            objects: vec![0; width * height],
+            heights: vec![0; width * height],
+            floors: vec![0; width * height],
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Self, MapError> {
+        let mut map: Option<Map> = None;
+        let mut name = String::new();
+        let mut par = 0;
+        // Plane being read, and how many of its rows were read so far
+        let mut plane: Option<(&'static str, usize)> = None;
+        let mut complete = vec![];
+        // Optional planes the map has, which have to be complete too
+        let mut optional = vec![];
+
+        for (index, line) in source.lines().enumerate() {
+            let number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some((current, row)) = plane {
+                let map = map.as_mut().ok_or(MapError::MissingSize)?;
+                if row < map.height {
+                    let values = line
+                        .split_whitespace()
+                        .map(|value| value.parse::<u8>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|_| MapError::InvalidNumber(number))?;
+                    if values.len() != map.width {
+                        return Err(MapError::WrongRowLength(number));
+                    }
+                    if let Some(&value) = values.iter().find(|&&value| match current {
+                        "walls" => !is_known_wall(value),
+                        "objects" => !is_known_object(value),
+                        _ => value > MAX_HEIGHT_STEPS,
+                    }) {
+                        return Err(match current {
+                            "walls" => MapError::UnknownWall(number, value),
+                            "objects" => MapError::UnknownObject(number, value),
+                            _ => MapError::TooHigh(number, value),
+                        });
+                    }
+                    let start = row * map.width;
+                    let target = match current {
+                        "walls" => &mut map.walls,
+                        "objects" => &mut map.objects,
+                        "heights" => &mut map.heights,
+                        _ => &mut map.floors,
+                    };
+                    target[start..start + map.width].copy_from_slice(&values);
+                    plane = Some((current, row + 1));
+                    if row + 1 == map.height {
+                        complete.push(current);
+                    }
+                    continue;
+                }
+                plane = None;
+            }
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "name" => name = value.trim().to_string(),
+                "par" => {
+                    par = value
+                        .trim()
+                        .parse()
+                        .map_err(|_| MapError::InvalidNumber(number))?
+                }
+                "size" if map.is_some() => return Err(MapError::RepeatedSize(number)),
+                "size" => {
+                    let size = value
+                        .split_whitespace()
+                        .map(|value| value.parse::<usize>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|_| MapError::InvalidNumber(number))?;
+                    match size[..] {
+                        [width, height] if width > MAX_MAP_SIZE || height > MAX_MAP_SIZE => {
+                            return Err(MapError::TooLarge(number))
+                        }
+                        [width, height] => map = Some(Map::new(width, height)),
+                        _ => return Err(MapError::InvalidNumber(number)),
+                    }
+                }
+                "walls" => plane = Some(("walls", 0)),
+                "objects" => plane = Some(("objects", 0)),
+                "heights" => {
+                    plane = Some(("heights", 0));
+                    optional.push("heights");
+                }
+                "floors" => {
+                    plane = Some(("floors", 0));
+                    optional.push("floors");
+                }
+                _ => return Err(MapError::UnknownLine(number)),
+            }
+            if plane.is_some() && map.is_none() {
+                return Err(MapError::MissingSize);
+            }
+        }
+
+        let mut map = map.ok_or(MapError::MissingSize)?;
+        for required in ["walls", "objects"].into_iter().chain(optional) {
+            if !complete.contains(&required) {
+                return Err(MapError::MissingPlane(required));
+            }
+        }
+        map.name = name;
+        map.par = par;
+        Ok(map)
+    }
+
+    /// Copy of the map with a different size, cropped or padded with empty cells.
+    pub fn resized(&self, width: usize, height: usize) -> Self {
+        let mut map = Map::new(width, height);
+        map.name = self.name.clone();
+        map.par = self.par;
+        for y in 0..height.min(self.height) {
+            for x in 0..width.min(self.width) {
+                map.set_tile(x, y, self.tile(x, y));
+                map.set_object(x, y, self.object(x, y));
+                map.heights[x + y * width] = self.wall_steps(x, y);
+                map.floors[x + y * width] = self.floor_steps(x, y);
+            }
+        }
+        map
+    }
+
+    pub fn tile(&self, x: usize, y: usize) -> u8 {
+        self.walls[x + y * self.width]
+    }
+
+    pub fn set_tile(&mut self, x: usize, y: usize, tile: u8) {
+        self.walls[x + y * self.width] = tile;
+    }
+
+    pub fn object(&self, x: usize, y: usize) -> u8 {
+        self.objects[x + y * self.width]
+    }
+
+    pub fn set_object(&mut self, x: usize, y: usize, object: u8) {
+        self.objects[x + y * self.width] = object;
+    }
+
+    /// Height of the wall in cell `x`, `y` in height steps, whether or not there is one.
+    pub fn wall_steps(&self, x: usize, y: usize) -> u8 {
+        self.heights.get(x + y * self.width).copied().unwrap_or(0)
+    }
+
+    /// Height of the floor of cell `x`, `y` in height steps.
+    pub fn floor_steps(&self, x: usize, y: usize) -> u8 {
+        self.floors.get(x + y * self.width).copied().unwrap_or(0)
+    }
+
+    /// Height of the floor of cell `x`, `y` in cells.
+    pub fn floor_height(&self, x: usize, y: usize) -> f32 {
+        self.floor_steps(x, y) as f32 / HEIGHT_STEPS as f32
+    }
+
+    /// Top of whatever stands in cell `x`, `y` in cells: its wall, or its floor if empty.
+    /// Doors and elevator switches are always one cell tall.
+    pub fn solid_top(&self, x: usize, y: usize) -> f32 {
+        let tile = self.tile(x, y);
+        let steps = match self.wall_steps(x, y) {
+            _ if tile == 0 => 0,
+            _ if is_door(tile) || is_elevator_switch(tile) => HEIGHT_STEPS,
+            0 => HEIGHT_STEPS,
+            steps => steps,
+        };
+        self.floor_height(x, y) + steps as f32 / HEIGHT_STEPS as f32
+    }
+
+    /// Top of the tallest wall or floor on the map, in cells.
+    pub fn max_top(&self) -> f32 {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| self.solid_top(x, y))
+            .fold(0.0, f32::max)
+    }
+
+    /// Centre of the player start cell and the angle it faces.
+    pub fn player_start(&self) -> Option<(f32, f32, f32)> {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let theta = match self.object(x, y) {
+                    PLAYER_START_NORTH => -PI / 2.0,
+                    PLAYER_START_EAST => 0.0,
+                    PLAYER_START_SOUTH => PI / 2.0,
+                    PLAYER_START_WEST => PI,
+                    _ => continue,
+                };
+                return Some((
+                    (x as f32 + 0.5) * TILE_WIDTH,
+                    (y as f32 + 0.5) * TILE_HEIGHT,
+                    theta,
+                ));
+            }
+        }
+        None
+    }
+
+    /// Number of cells holding the given object.
+    pub fn count_objects(&self, object: u8) -> usize {
+        self.objects.iter().filter(|&&o| o == object).count()
+    }
+
+    /// Cell containing a point in world coordinates, if it is on the map.
+    pub fn cell_at(&self, x: f32, y: f32) -> Option<(usize, usize)> {
+        let map_x = (x / TILE_WIDTH).floor();
+        let map_y = (y / TILE_HEIGHT).floor();
+        if map_x < 0.0 || map_y < 0.0 {
+            return None;
+        }
+        let (map_x, map_y) = (map_x as usize, map_y as usize);
+        if map_x >= self.width || map_y >= self.height {
+            return None;
+        }
+        Some((map_x, map_y))
+    }
+}
+
+impl fmt::Display for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# wolfenstein-rs map")?;
+        writeln!(f, "name {}", self.name)?;
+        writeln!(f, "par {}", self.par)?;
+        writeln!(f, "size {} {}", self.width, self.height)?;
+        let planes = [
+            ("walls", &self.walls),
+            ("objects", &self.objects),
+            ("heights", &self.heights),
+            ("floors", &self.floors),
+        ];
+        for (label, plane) in planes {
+            // Flat maps leave out the height planes
+            if matches!(label, "heights" | "floors") && plane.iter().all(|&value| value == 0) {
+                continue;
+            }
+            writeln!(f, "{label}")?;
+            for row in plane.chunks(self.width) {
+                let row: Vec<String> = row.iter().map(|value| format!("{value:3}")).collect();
+                writeln!(f, "{}", row.join(" "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::levels::LEVELS;
+
+    #[test]
+    fn built_in_levels_parse() {
+        for level in LEVELS {
+            assert!(
+                Map::parse(level.source).is_ok(),
+                "{} does not parse",
+                level.name()
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_codes() {
+        let source = "size 2 1\nwalls\n1 9\nobjects\n0 0\n";
+        assert!(matches!(
+            Map::parse(source),
+            Err(MapError::UnknownWall(3, 9))
+        ));
+        let source = "size 2 1\nwalls\n13 14\nobjects\n0 0\n";
+        assert!(matches!(
+            Map::parse(source),
+            Err(MapError::UnknownWall(3, 14))
+        ));
+        let source = "size 2 1\nwalls\n1 0\nobjects\n0 200\n";
+        assert!(matches!(
+            Map::parse(source),
+            Err(MapError::UnknownObject(5, 200))
+        ));
+    }
+
+    #[test]
+    fn rejects_oversized_maps() {
+        let source = format!("size {} 1\n", MAX_MAP_SIZE + 1);
+        assert!(matches!(Map::parse(&source), Err(MapError::TooLarge(1))));
+    }
+
+    #[test]
+    fn rejects_heights_above_the_highest() {
+        let source = "size 2 1\nwalls\n1 0\nobjects\n0 0\nheights\n32 0\nfloors\n0 33\n";
+        assert!(matches!(Map::parse(source), Err(MapError::TooHigh(9, 33))));
+        let source = "size 2 1\nwalls\n1 0\nobjects\n0 0\nheights\n0 255\n";
+        assert!(matches!(Map::parse(source), Err(MapError::TooHigh(7, 255))));
+    }
+
+    #[test]
+    fn rejects_a_repeated_size() {
+        let source = "size 2 1\nwalls\n1 0\nsize 3 1\nobjects\n0 0 0\n";
+        assert!(matches!(Map::parse(source), Err(MapError::RepeatedSize(4))));
+    }
+}
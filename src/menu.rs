@@ -0,0 +1,491 @@
+use palette::Srgb;
+
+use crate::{
+    assets::BitmapFont,
+    console::{CvarKind, CVARS},
+    difficulty::Difficulty,
+    geo::*,
+    graphics::*,
+    rng::time_seed,
+    save::*,
+    World, HEIGHT, VIEW_HEIGHT, WIDTH,
+};
+
+const BACKGROUND_COLOR: Srgb = Srgb::new(0.53, 0.0, 0.0);
+const BOX_COLOR: Srgb = Srgb::new(0.35, 0.0, 0.0);
+const BORDER_COLOR: Srgb = Srgb::new(0.75, 0.15, 0.15);
+const TITLE_COLOR: Srgb = Srgb::new(1.0, 0.85, 0.2);
+const ITEM_COLOR: Srgb = Srgb::new(0.7, 0.7, 0.7);
+const SELECTED_COLOR: Srgb = Srgb::new(1.0, 1.0, 1.0);
+const DISABLED_COLOR: Srgb = Srgb::new(0.4, 0.25, 0.25);
+const HINT_COLOR: Srgb = Srgb::new(0.85, 0.6, 0.6);
+
+const TITLE_Y: f32 = 16.0;
+const ITEMS_Y: f32 = 72.0;
+const ROW_HEIGHT: f32 = 22.0;
+const BOX_WIDTH: f32 = 400.0;
+/// Items of screens with a value, like the options, are left aligned at this offset from
+/// the box's left edge, values right aligned at the same distance from its right edge.
+const COLUMN_MARGIN: f32 = 24.0;
+
+/// Cvars adjustable from the Options screen, with their label and the step Left and Right
+/// change them by.
+const OPTIONS: [(&str, &str, f32); 10] = [
+    ("Volume", "volume", 0.1),
+    ("Music volume", "music_volume", 0.1),
+    ("Walking speed", "speed", 0.5),
+    ("Turning speed", "rotation_speed", 0.01),
+    ("Mouse sensitivity", "mouse_sensitivity", 0.001),
+    ("Field of view", "fov", 5.0),
+    ("Fog", "fog", 2.0),
+    ("Render scale", "render_scale", 0.1),
+    ("View bobbing", "view_bob", 1.0),
+    ("Enemies on automap", "automap_enemies", 1.0),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuScreen {
+    /// Shown at startup, and when leaving the pause menu for it.
+    Main,
+    /// Shown when pressing Esc during the game.
+    Pause,
+    Skill,
+    Load,
+    Save,
+    Options,
+}
+
+impl MenuScreen {
+    fn title(&self) -> &'static str {
+        match self {
+            MenuScreen::Main => "WOLFENSTEIN-RS",
+            MenuScreen::Pause => "PAUSED",
+            MenuScreen::Skill => "HOW TOUGH ARE YOU?",
+            MenuScreen::Load => "LOAD GAME",
+            MenuScreen::Save => "SAVE GAME",
+            MenuScreen::Options => "OPTIONS",
+        }
+    }
+
+    /// Whether the game stays visible around the menu box.
+    fn is_overlay(&self) -> bool {
+        *self == MenuScreen::Pause
+    }
+}
+
+/// A line of a menu screen.
+struct MenuItem {
+    label: String,
+    /// Shown right aligned, e.g. an option's setting.
+    value: Option<String>,
+    enabled: bool,
+}
+
+impl MenuItem {
+    fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            value: None,
+            enabled: true,
+        }
+    }
+
+    fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    fn value(mut self, value: String) -> Self {
+        self.value = Some(value);
+        self
+    }
+}
+
+/// The in-game menus, drawn into the frame like the rest of the game. The game is paused
+/// while one is open.
+#[derive(Debug, Clone)]
+pub struct Menu {
+    pub screen: Option<MenuScreen>,
+    pub selected: usize,
+    /// Whether a game has been started or loaded from the menu, so there is one to go back to.
+    pub started: bool,
+    /// Set by the Quit item, for the main loop to exit on.
+    pub quit: bool,
+    /// Screen Esc goes back to from the skill, load, save and options screens.
+    parent: MenuScreen,
+    /// Slot descriptions, read when the load or save screen opens.
+    slots: Vec<Option<String>>,
+}
+
+impl Default for Menu {
+    fn default() -> Self {
+        Self {
+            screen: None,
+            selected: 0,
+            started: false,
+            quit: false,
+            parent: MenuScreen::Main,
+            slots: vec![],
+        }
+    }
+}
+
+impl Menu {
+    pub fn is_open(&self) -> bool {
+        self.screen.is_some()
+    }
+
+    pub fn open(&mut self, screen: MenuScreen) {
+        if matches!(screen, MenuScreen::Load | MenuScreen::Save) {
+            self.slots = list_slots();
+        }
+        if let Some(current @ (MenuScreen::Main | MenuScreen::Pause)) = self.screen {
+            self.parent = current;
+        }
+        self.screen = Some(screen);
+        self.selected = 0;
+    }
+
+    pub fn close(&mut self) {
+        self.screen = None;
+    }
+
+    /// Go back a screen, closing the menu from the pause menu and, once a game is going,
+    /// from the main menu.
+    pub fn back(&mut self) {
+        match self.screen {
+            Some(MenuScreen::Main) if !self.started => {}
+            Some(MenuScreen::Main | MenuScreen::Pause) | None => self.close(),
+            Some(_) => {
+                let parent = self.parent;
+                self.open(parent);
+            }
+        }
+    }
+}
+
+/// Box the items of `screen` are drawn in.
+fn menu_box(screen: MenuScreen, rows: usize) -> Rect {
+    let height = if screen.is_overlay() {
+        ITEMS_Y - TITLE_Y + rows as f32 * ROW_HEIGHT + 16.0
+    } else {
+        VIEW_HEIGHT as f32 - ITEMS_Y + 8.0
+    };
+    let top = if screen.is_overlay() {
+        (VIEW_HEIGHT as f32 - height) / 2.0
+    } else {
+        ITEMS_Y - 8.0
+    };
+    Rect::new((WIDTH as f32 - BOX_WIDTH) / 2.0, top, BOX_WIDTH, height)
+}
+
+/// Top of the first item of `screen`.
+fn items_top(screen: MenuScreen, rows: usize) -> f32 {
+    if screen.is_overlay() {
+        menu_box(screen, rows).y + ITEMS_Y - TITLE_Y
+    } else {
+        ITEMS_Y
+    }
+}
+
+/// Shown value of option `index`, rounded to its step.
+fn option_value(world: &World, index: usize) -> Option<String> {
+    let (_, name, step) = OPTIONS[index];
+    let cvar = CVARS.iter().find(|cvar| cvar.name == name)?;
+    let value = (cvar.get)(world);
+    Some(match cvar.kind {
+        CvarKind::Bool => if value != 0.0 { "on" } else { "off" }.to_string(),
+        CvarKind::Float { .. } => {
+            let decimals = (-step.log10()).ceil().max(0.0) as usize;
+            format!("{value:.decimals$}")
+        }
+    })
+}
+
+/// An option's value with the arrows around it that hint at changing it.
+fn value_text(value: &str) -> String {
+    format!("< {value} >")
+}
+
+/// `text` cut short to fit `width` pixels when drawn at twice the font size.
+fn fit(font: &BitmapFont, text: &str, width: f32) -> String {
+    let fits = |text: &str| font.measure(text, false) as f32 * 2.0 <= width;
+    if fits(text) {
+        return text.to_string();
+    }
+    let mut text = text.to_string();
+    while !text.is_empty() && !fits(&format!("{text}...")) {
+        text.pop();
+    }
+    format!("{text}...")
+}
+
+impl World {
+    fn menu_items(&self) -> Vec<MenuItem> {
+        let Some(screen) = self.menu.screen else {
+            return vec![];
+        };
+        let can_save = self.menu.started && self.can_save();
+        match screen {
+            MenuScreen::Main => vec![
+                MenuItem::new("New Game"),
+                MenuItem::new("Load Game"),
+                MenuItem::new("Save Game").enabled(can_save),
+                MenuItem::new("Options"),
+                MenuItem::new("Back to Game").enabled(self.menu.started),
+                MenuItem::new("Quit"),
+            ],
+            MenuScreen::Pause => vec![
+                MenuItem::new("Resume"),
+                MenuItem::new("Save Game").enabled(can_save),
+                MenuItem::new("Load Game"),
+                MenuItem::new("Options"),
+                MenuItem::new("Main Menu"),
+            ],
+            MenuScreen::Skill => Difficulty::ALL
+                .iter()
+                .map(|difficulty| MenuItem::new(difficulty.name()))
+                .collect(),
+            MenuScreen::Load | MenuScreen::Save => self
+                .menu
+                .slots
+                .iter()
+                .enumerate()
+                .map(|(slot, description)| {
+                    let label = match description {
+                        Some(description) => format!("{slot}. {description}"),
+                        None => format!("{slot}. -"),
+                    };
+                    MenuItem::new(label)
+                        .enabled(description.is_some() || screen == MenuScreen::Save)
+                })
+                .collect(),
+            MenuScreen::Options => OPTIONS
+                .iter()
+                .enumerate()
+                .map(|(i, (label, _, _))| {
+                    MenuItem::new(*label).value(option_value(self, i).unwrap_or_default())
+                })
+                .collect(),
+        }
+    }
+
+    /// Open the pause menu, or the main menu before a game was started.
+    pub fn open_menu(&mut self) {
+        if self.menu.started {
+            self.menu.open(MenuScreen::Pause);
+        } else {
+            self.menu.open(MenuScreen::Main);
+        }
+    }
+
+    /// Move the highlight by `step` items, skipping disabled ones.
+    pub fn menu_move(&mut self, step: isize) {
+        let items = self.menu_items();
+        let count = items.len() as isize;
+        if count == 0 {
+            return;
+        }
+        let mut index = self.menu.selected as isize;
+        for _ in 0..count {
+            index = (index + step).rem_euclid(count);
+            if items[index as usize].enabled {
+                self.menu.selected = index as usize;
+                return;
+            }
+        }
+    }
+
+    /// Do what the highlighted item says.
+    pub fn menu_select(&mut self) {
+        let Some(screen) = self.menu.screen else {
+            return;
+        };
+        let index = self.menu.selected;
+        if !self
+            .menu_items()
+            .get(index)
+            .is_some_and(|item| item.enabled)
+        {
+            return;
+        }
+        match (screen, index) {
+            (MenuScreen::Main, 0) => self.menu.open(MenuScreen::Skill),
+            (MenuScreen::Main, 1) | (MenuScreen::Pause, 2) => {
+                // Only a failed load should show up under the slots
+                self.message = None;
+                self.menu.open(MenuScreen::Load);
+            }
+            (MenuScreen::Main, 2) | (MenuScreen::Pause, 1) => self.menu.open(MenuScreen::Save),
+            (MenuScreen::Main, 3) | (MenuScreen::Pause, 3) => self.menu.open(MenuScreen::Options),
+            (MenuScreen::Main, 4) | (MenuScreen::Pause, 0) => self.menu.close(),
+            (MenuScreen::Main, _) => self.menu.quit = true,
+            (MenuScreen::Pause, _) => self.menu.open(MenuScreen::Main),
+            (MenuScreen::Skill, _) if self.refuse_while_recording("start a new game") => {
+                self.menu.close();
+            }
+            (MenuScreen::Skill, _) => {
+                self.new_game(0, time_seed(), Difficulty::ALL[index]);
+                self.menu.started = true;
+                self.menu.close();
+            }
+            // A failed load leaves the menu open, with the reason as the message
+            (MenuScreen::Load, slot) => {
+                if self.load_from_slot(slot) {
+                    self.menu.started = true;
+                    self.menu.close();
+                }
+            }
+            (MenuScreen::Save, slot) => {
+                self.save_to_slot(slot);
+                self.menu.close();
+            }
+            (MenuScreen::Options, _) => self.menu_adjust(1.0),
+        }
+    }
+
+    /// Change the highlighted option by a step in `direction`, toggling on and off ones.
+    pub fn menu_adjust(&mut self, direction: f32) {
+        if self.menu.screen != Some(MenuScreen::Options) {
+            return;
+        }
+        let Some(&(_, name, step)) = OPTIONS.get(self.menu.selected) else {
+            return;
+        };
+        let Some(cvar) = CVARS.iter().find(|cvar| cvar.name == name) else {
+            return;
+        };
+        let value = (cvar.get)(self);
+        let value = match cvar.kind {
+            CvarKind::Bool => (value == 0.0) as u8 as f32,
+            CvarKind::Float { min, max } => {
+                (((value + direction * step) / step).round() * step).clamp(min, max)
+            }
+        };
+        (cvar.set)(self, value);
+    }
+
+    /// Item under the frame position `x`, `y`.
+    fn menu_item_at(&self, x: f32, y: f32) -> Option<usize> {
+        let screen = self.menu.screen?;
+        let rows = self.menu_items().len();
+        let area = menu_box(screen, rows);
+        if x < area.x || x >= area.x + area.width {
+            return None;
+        }
+        let row = (y - items_top(screen, rows)) / ROW_HEIGHT;
+        (row >= 0.0 && (row as usize) < rows).then_some(row as usize)
+    }
+
+    /// Highlight the item under the mouse.
+    pub fn menu_hover(&mut self, x: f32, y: f32) {
+        if let Some(index) = self.menu_item_at(x, y) {
+            if self.menu_items()[index].enabled {
+                self.menu.selected = index;
+            }
+        }
+    }
+
+    /// Click at frame position `x`, `y`. Options go down when clicked left of the value's
+    /// middle, and up otherwise.
+    pub fn menu_click(&mut self, x: f32, y: f32) {
+        let Some(index) = self.menu_item_at(x, y) else {
+            return;
+        };
+        self.menu.selected = index;
+        if self.menu.screen == Some(MenuScreen::Options) {
+            let area = menu_box(MenuScreen::Options, OPTIONS.len());
+            let value = value_text(&option_value(self, index).unwrap_or_default());
+            let width = self.assets.font.measure(&value, false) as f32 * 2.0;
+            let value_middle = area.x + area.width - COLUMN_MARGIN - width / 2.0;
+            self.menu_adjust(if x < value_middle { -1.0 } else { 1.0 });
+        } else {
+            self.menu_select();
+        }
+    }
+
+    pub fn draw_menu(&self, frame: &mut [u8]) {
+        let Some(screen) = self.menu.screen else {
+            return;
+        };
+        let items = self.menu_items();
+        let area = menu_box(screen, items.len());
+        if !screen.is_overlay() {
+            ColorRect::new(0.0, 0.0, WIDTH as f32, HEIGHT as f32, BACKGROUND_COLOR).draw(frame);
+        }
+        ColorRect::new(
+            area.x - 2.0,
+            area.y - 2.0,
+            area.width + 4.0,
+            area.height + 4.0,
+            BORDER_COLOR,
+        )
+        .draw(frame);
+        ColorRect::new(area.x, area.y, area.width, area.height, BOX_COLOR).draw(frame);
+
+        let font = &self.assets.font;
+        let center = WIDTH as f32 / 2.0;
+        let (title_y, title_scale) = if screen.is_overlay() {
+            (area.y + 12.0, 2.0)
+        } else {
+            (TITLE_Y, 3.0)
+        };
+        Text::new(font, center, title_y, screen.title())
+            .scale(title_scale)
+            .color(TITLE_COLOR)
+            .align(Align::Center)
+            .draw(frame);
+
+        let top = items_top(screen, items.len());
+        let columns = items.iter().any(|item| item.value.is_some())
+            || matches!(screen, MenuScreen::Load | MenuScreen::Save);
+        for (i, item) in items.iter().enumerate() {
+            let y = top + i as f32 * ROW_HEIGHT + 2.0;
+            let color = if !item.enabled {
+                DISABLED_COLOR
+            } else if i == self.menu.selected {
+                SELECTED_COLOR
+            } else {
+                ITEM_COLOR
+            };
+            if columns {
+                let label = fit(font, &item.label, area.width - 2.0 * COLUMN_MARGIN);
+                Text::new(font, area.x + COLUMN_MARGIN, y, &label)
+                    .scale(2.0)
+                    .color(color)
+                    .draw(frame);
+            } else {
+                Text::new(font, center, y, &item.label)
+                    .scale(2.0)
+                    .color(color)
+                    .align(Align::Center)
+                    .draw(frame);
+            }
+            if let Some(value) = &item.value {
+                let value = value_text(value);
+                Text::new(font, area.x + area.width - COLUMN_MARGIN, y, &value)
+                    .scale(2.0)
+                    .color(color)
+                    .align(Align::Right)
+                    .draw(frame);
+            }
+            if i == self.menu.selected && item.enabled {
+                Text::new(font, area.x + 8.0, y, ">")
+                    .scale(2.0)
+                    .color(TITLE_COLOR)
+                    .draw(frame);
+            }
+        }
+        if !screen.is_overlay() {
+            let hint = match (&self.message, screen) {
+                (Some(message), MenuScreen::Load) => message.text.as_str(),
+                (_, MenuScreen::Options) => "Arrows to change, Esc to go back",
+                _ => "Arrows to move, Enter to select, Esc to go back",
+            };
+            Text::new(font, center, HEIGHT as f32 - 14.0, hint)
+                .color(HINT_COLOR)
+                .align(Align::Center)
+                .draw(frame);
+        }
+    }
+}
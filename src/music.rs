@@ -0,0 +1,153 @@
+use std::fmt;
+
+use crate::{audio::SAMPLE_RATE, opl::Opl};
+
+/// Rate the delays in the game's IMF music are counted at, in Hz.
+pub const IMF_RATE: u32 = 700;
+
+/// One register write, followed by a wait before the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImfCommand {
+    pub register: u8,
+    pub value: u8,
+    /// Ticks at `IMF_RATE` to wait after the write.
+    pub delay: u16,
+}
+
+#[derive(Debug)]
+pub enum ImfError {
+    /// The data stops in the middle of a command.
+    Truncated,
+    Empty,
+}
+
+impl fmt::Display for ImfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImfError::Truncated => write!(f, "music data ends in the middle of a command"),
+            ImfError::Empty => write!(f, "music has no commands"),
+        }
+    }
+}
+
+impl std::error::Error for ImfError {}
+
+/// A piece of AdLib music: the stream of OPL2 register writes the original sent to the card.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Imf {
+    pub commands: Vec<ImfCommand>,
+}
+
+impl Imf {
+    /// Read an IMF chunk as stored by the game, starting with the length of the commands
+    /// in bytes. Files without the length, as some tools write them, are read whole.
+    pub fn parse(bytes: &[u8]) -> Result<Self, ImfError> {
+        let data = match bytes {
+            [low, high, rest @ ..] => {
+                let length = u16::from_le_bytes([*low, *high]) as usize;
+                if length > 0 && length.is_multiple_of(4) && length <= rest.len() {
+                    &rest[..length]
+                } else {
+                    bytes
+                }
+            }
+            _ => bytes,
+        };
+        if !data.len().is_multiple_of(4) {
+            return Err(ImfError::Truncated);
+        }
+        let commands: Vec<_> = data
+            .chunks_exact(4)
+            .map(|command| ImfCommand {
+                register: command[0],
+                value: command[1],
+                delay: u16::from_le_bytes([command[2], command[3]]),
+            })
+            .collect();
+        if commands.iter().all(|command| command.delay == 0) {
+            return Err(ImfError::Empty);
+        }
+        Ok(Self { commands })
+    }
+}
+
+/// A music track shipped with the game.
+pub struct Track {
+    pub name: &'static str,
+    data: &'static [u8],
+}
+
+impl Track {
+    pub fn find(name: &str) -> Option<&'static Track> {
+        TRACKS.iter().find(|track| track.name == name)
+    }
+
+    pub fn imf(&self) -> Imf {
+        Imf::parse(self.data).expect("built-in music is valid")
+    }
+}
+
+pub const TRACKS: [Track; 3] = [
+    Track {
+        name: "march",
+        data: include_bytes!("../assets/music/march.imf"),
+    },
+    Track {
+        name: "alarm",
+        data: include_bytes!("../assets/music/alarm.imf"),
+    },
+    Track {
+        name: "bonus",
+        data: include_bytes!("../assets/music/bonus.imf"),
+    },
+];
+
+/// Plays an IMF track on an emulated OPL2, looping at the end.
+pub struct MusicPlayer {
+    pub name: String,
+    imf: Imf,
+    opl: Opl,
+    /// Index of the next command to send.
+    next: usize,
+    /// Time left before the next command, in samples times `IMF_RATE` to stay exact.
+    wait: i64,
+}
+
+impl MusicPlayer {
+    pub fn new(name: &str, imf: Imf) -> Self {
+        let mut opl = Opl::new(SAMPLE_RATE);
+        // The game turns on the waveform select before playing anything
+        opl.write(0x01, 0x20);
+        Self {
+            name: name.to_string(),
+            imf,
+            opl,
+            next: 0,
+            wait: 0,
+        }
+    }
+
+    /// Send commands until the next wait.
+    fn advance(&mut self) {
+        while self.wait <= 0 {
+            if self.next >= self.imf.commands.len() {
+                self.next = 0;
+            }
+            let command = self.imf.commands[self.next];
+            self.opl.write(command.register, command.value);
+            self.wait += command.delay as i64 * SAMPLE_RATE as i64;
+            self.next += 1;
+        }
+    }
+
+    /// Add the next interleaved stereo samples at `volume` to `out`.
+    pub fn render(&mut self, out: &mut [f32], volume: f32) {
+        for frame in out.chunks_exact_mut(2) {
+            self.advance();
+            let sample = self.opl.sample() * volume;
+            frame[0] += sample;
+            frame[1] += sample;
+            self.wait -= IMF_RATE as i64;
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+
+use crate::map::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ObjectKind {
+    GoldKey,
+    SilverKey,
+    Cross,
+    Chalice,
+    Chest,
+    Crown,
+    FirstAid,
+    AmmoClip,
+    MachineGun,
+    ChainGun,
+}
+
+impl ObjectKind {
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            GOLD_KEY => Some(ObjectKind::GoldKey),
+            SILVER_KEY => Some(ObjectKind::SilverKey),
+            CROSS => Some(ObjectKind::Cross),
+            CHALICE => Some(ObjectKind::Chalice),
+            CHEST => Some(ObjectKind::Chest),
+            CROWN => Some(ObjectKind::Crown),
+            FIRST_AID => Some(ObjectKind::FirstAid),
+            AMMO_CLIP => Some(ObjectKind::AmmoClip),
+            MACHINE_GUN => Some(ObjectKind::MachineGun),
+            CHAIN_GUN => Some(ObjectKind::ChainGun),
+            _ => None,
+        }
+    }
+
+    /// Frame in the sprites sheet.
+    pub fn sprite(&self) -> usize {
+        match self {
+            ObjectKind::GoldKey => 0,
+            ObjectKind::SilverKey => 1,
+            ObjectKind::Cross => 2,
+            ObjectKind::Chalice => 3,
+            ObjectKind::Chest => 4,
+            ObjectKind::Crown => 5,
+            ObjectKind::FirstAid => 6,
+            ObjectKind::AmmoClip => 7,
+            ObjectKind::MachineGun => 8,
+            ObjectKind::ChainGun => 9,
+        }
+    }
+
+    /// Points scored for picking up treasure, `None` for anything else.
+    pub fn treasure_points(&self) -> Option<u32> {
+        match self {
+            ObjectKind::Cross => Some(100),
+            ObjectKind::Chalice => Some(500),
+            ObjectKind::Chest => Some(1000),
+            ObjectKind::Crown => Some(5000),
+            _ => None,
+        }
+    }
+}
+
+/// Something lying on the floor, drawn as a billboard.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Object {
+    pub x: f32,
+    pub y: f32,
+    pub kind: ObjectKind,
+}
+
+/// Create objects from the object plane, centred in their cells.
+pub fn spawn_objects(map: &Map) -> Vec<Object> {
+    let mut objects = vec![];
+    for y in 0..map.height {
+        for x in 0..map.width {
+            if let Some(kind) = ObjectKind::from_code(map.object(x, y)) {
+                objects.push(Object {
+                    x: (x as f32 + 0.5) * TILE_WIDTH,
+                    y: (y as f32 + 0.5) * TILE_HEIGHT,
+                    kind,
+                });
+            }
+        }
+    }
+    objects
+}
@@ -0,0 +1,375 @@
+use std::f32::consts::TAU;
+
+/// Clock the OPL2's frequency numbers are relative to, in Hz.
+const OPL_RATE: f32 = 49716.0;
+/// Attenuation, in dB, at which an operator is silent.
+const SILENT_DB: f32 = 96.0;
+/// Frequency multipliers picked by the MULT bits, halves included.
+const MULTIPLIERS: [f32; 16] = [
+    0.5, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 10.0, 12.0, 12.0, 15.0, 15.0,
+];
+/// Key scale attenuation in dB for the top octave, by the top four bits of the frequency number.
+const KSL_DB: [f32; 16] = [
+    0.0, 9.0, 12.0, 13.875, 15.0, 16.125, 16.875, 17.625, 18.0, 18.75, 19.125, 19.5, 19.875, 20.25,
+    20.625, 21.0,
+];
+/// Milliseconds an attack takes at rate 1, halving for every rate above.
+const ATTACK_MS: f32 = 2826.24;
+/// Milliseconds a decay or release from full volume to silence takes at rate 1.
+const DECAY_MS: f32 = 39280.64;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Stage {
+    #[default]
+    Off,
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+/// One of the two oscillators of a channel, with its own envelope.
+#[derive(Debug, Clone, Default)]
+struct Operator {
+    tremolo: bool,
+    vibrato: bool,
+    /// Hold at the sustain level while the key is down, instead of fading out.
+    sustained: bool,
+    /// Envelopes get faster with pitch.
+    key_scale_rate: bool,
+    multiplier: u8,
+    key_scale_level: u8,
+    total_level: u8,
+    attack: u8,
+    decay: u8,
+    sustain_level: u8,
+    release: u8,
+    waveform: u8,
+    /// Position in the wave, in cycles.
+    phase: f32,
+    /// Current attenuation from the envelope, in dB.
+    envelope: f32,
+    stage: Stage,
+    /// Last two outputs, fed back into the modulator.
+    out: [f32; 2],
+}
+
+impl Operator {
+    fn write(&mut self, base: u8, value: u8) {
+        match base {
+            0x20 => {
+                self.tremolo = value & 0x80 != 0;
+                self.vibrato = value & 0x40 != 0;
+                self.sustained = value & 0x20 != 0;
+                self.key_scale_rate = value & 0x10 != 0;
+                self.multiplier = value & 0x0f;
+            }
+            0x40 => {
+                self.key_scale_level = value >> 6;
+                self.total_level = value & 0x3f;
+            }
+            0x60 => {
+                self.attack = value >> 4;
+                self.decay = value & 0x0f;
+            }
+            0x80 => {
+                self.sustain_level = value >> 4;
+                self.release = value & 0x0f;
+            }
+            0xe0 => self.waveform = value & 0x03,
+            _ => {}
+        }
+    }
+
+    fn key_on(&mut self) {
+        self.stage = Stage::Attack;
+        self.phase = 0.0;
+    }
+
+    fn key_off(&mut self) {
+        if self.stage != Stage::Off {
+            self.stage = Stage::Release;
+        }
+    }
+
+    /// Rate doublings above rate 1, taking key scaling into account. `None` for rate 0,
+    /// which never moves.
+    fn octaves(&self, rate: u8, key_code: u8) -> Option<f32> {
+        if rate == 0 {
+            return None;
+        }
+        let offset = if self.key_scale_rate {
+            key_code
+        } else {
+            key_code >> 2
+        };
+        let rate = (rate as u32 * 4 + offset as u32).min(63);
+        Some((rate as f32 - 4.0) / 4.0)
+    }
+
+    /// dB the envelope moves by per sample at `rate`.
+    fn decay_step(&self, rate: u8, key_code: u8, sample_rate: f32) -> f32 {
+        match self.octaves(rate, key_code) {
+            Some(octaves) => {
+                SILENT_DB / (DECAY_MS / 1000.0 / octaves.exp2() * sample_rate).max(1.0)
+            }
+            None => 0.0,
+        }
+    }
+
+    fn update_envelope(&mut self, key_code: u8, sample_rate: f32) {
+        let sustain_db = if self.sustain_level == 15 {
+            93.0
+        } else {
+            self.sustain_level as f32 * 3.0
+        };
+        match self.stage {
+            Stage::Off => self.envelope = SILENT_DB,
+            Stage::Attack => {
+                match self.octaves(self.attack, key_code) {
+                    // The fastest rates attack at once
+                    Some(octaves) if octaves >= 14.0 => self.envelope = 0.0,
+                    Some(octaves) => {
+                        let samples = ATTACK_MS / 1000.0 / octaves.exp2() * sample_rate;
+                        // Exponential approach to full volume, as on the chip
+                        let k = ((SILENT_DB + 1.0).ln() / samples.max(1.0)).min(1.0);
+                        self.envelope -= (self.envelope + 1.0) * k;
+                    }
+                    None => {}
+                }
+                if self.envelope <= 0.0 {
+                    self.envelope = 0.0;
+                    self.stage = Stage::Decay;
+                }
+            }
+            Stage::Decay => {
+                self.envelope += self.decay_step(self.decay, key_code, sample_rate);
+                if self.envelope >= sustain_db {
+                    self.envelope = sustain_db;
+                    self.stage = Stage::Sustain;
+                }
+            }
+            Stage::Sustain => {
+                if !self.sustained {
+                    self.envelope += self.decay_step(self.release, key_code, sample_rate);
+                }
+            }
+            Stage::Release => {
+                self.envelope += self.decay_step(self.release, key_code, sample_rate);
+            }
+        }
+        if self.envelope >= SILENT_DB {
+            self.envelope = SILENT_DB;
+            if self.stage != Stage::Attack {
+                self.stage = Stage::Off;
+            }
+        }
+    }
+
+    fn wave(&self, phase: f32, waveform_select: bool) -> f32 {
+        let phase = phase.rem_euclid(1.0);
+        let sine = (phase * TAU).sin();
+        match if waveform_select { self.waveform } else { 0 } {
+            0 => sine,
+            // Half sine
+            1 => sine.max(0.0),
+            // Absolute sine
+            2 => sine.abs(),
+            // Quarter sine pulses
+            _ => {
+                if phase % 0.5 < 0.25 {
+                    sine.abs()
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    /// Next output, from -1 to 1, with the phase shifted by `modulation` cycles.
+    fn sample(&mut self, channel: &ChannelPitch, lfo: &Lfo, modulation: f32, opl: &Opl) -> f32 {
+        self.update_envelope(channel.key_code, opl.sample_rate);
+        if self.stage == Stage::Off {
+            return 0.0;
+        }
+        let scale = match self.key_scale_level {
+            0 => 0.0,
+            1 => 0.5,
+            2 => 0.25,
+            _ => 1.0,
+        };
+        let key_scale_db = (KSL_DB[channel.fnum as usize >> 6] - 6.0 * (7 - channel.block) as f32)
+            .max(0.0)
+            * scale;
+        let mut attenuation = self.envelope + self.total_level as f32 * 0.75 + key_scale_db;
+        if self.tremolo {
+            attenuation += lfo.tremolo_db;
+        }
+        let output = if attenuation >= SILENT_DB {
+            0.0
+        } else {
+            self.wave(self.phase + modulation, opl.waveform_select)
+                * 10f32.powf(-attenuation / 20.0)
+        };
+        let mut frequency = channel.frequency * MULTIPLIERS[self.multiplier as usize];
+        if self.vibrato {
+            frequency *= lfo.vibrato;
+        }
+        self.phase = (self.phase + frequency / opl.sample_rate).fract();
+        output
+    }
+}
+
+/// Pitch of a channel as needed by its operators.
+struct ChannelPitch {
+    fnum: u16,
+    block: u8,
+    frequency: f32,
+    key_code: u8,
+}
+
+/// Tremolo and vibrato for the current sample.
+struct Lfo {
+    tremolo_db: f32,
+    vibrato: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Channel {
+    /// 10-bit frequency number.
+    fnum: u16,
+    /// Octave.
+    block: u8,
+    key_on: bool,
+    feedback: u8,
+    /// Both operators are heard, instead of the first modulating the second.
+    additive: bool,
+    operators: [Operator; 2],
+}
+
+impl Channel {
+    fn pitch(&self) -> ChannelPitch {
+        ChannelPitch {
+            fnum: self.fnum,
+            block: self.block,
+            frequency: self.fnum as f32 * OPL_RATE / (1u32 << (20 - self.block)) as f32,
+            key_code: self.block * 2 + ((self.fnum >> 9) & 1) as u8,
+        }
+    }
+
+    fn is_silent(&self) -> bool {
+        self.operators.iter().all(|op| op.stage == Stage::Off)
+    }
+}
+
+/// A software Yamaha YM3812 (OPL2), as on the AdLib card the game's music was written for.
+/// Only the nine melodic channels are emulated; the rhythm mode, which the music
+/// doesn't use, is not.
+#[derive(Debug, Clone)]
+pub struct Opl {
+    sample_rate: f32,
+    channels: [Channel; 9],
+    /// Whether the waveform select registers are honoured, otherwise everything is a sine.
+    waveform_select: bool,
+    deep_tremolo: bool,
+    deep_vibrato: bool,
+    /// Samples generated so far, driving the tremolo and vibrato.
+    samples: u64,
+}
+
+impl Opl {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate: sample_rate as f32,
+            channels: Default::default(),
+            waveform_select: false,
+            deep_tremolo: false,
+            deep_vibrato: false,
+            samples: 0,
+        }
+    }
+
+    /// Silence every channel and clear all registers.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.sample_rate as u32);
+    }
+
+    /// Write `value` to register `register`, like an `out` to the AdLib's data port.
+    pub fn write(&mut self, register: u8, value: u8) {
+        match register {
+            0x01 => self.waveform_select = value & 0x20 != 0,
+            0x20..=0x35 | 0x40..=0x55 | 0x60..=0x75 | 0x80..=0x95 | 0xe0..=0xf5 => {
+                // Operators are laid out in three groups of six, with gaps in between
+                let offset = register & 0x1f;
+                let group = offset / 8;
+                let index = offset % 8;
+                if index < 6 {
+                    let channel = (group * 3 + index % 3) as usize;
+                    let operator = (index / 3) as usize;
+                    self.channels[channel].operators[operator].write(register & 0xe0, value);
+                }
+            }
+            0xa0..=0xa8 => {
+                let channel = &mut self.channels[(register - 0xa0) as usize];
+                channel.fnum = (channel.fnum & 0x300) | value as u16;
+            }
+            0xb0..=0xb8 => {
+                let channel = &mut self.channels[(register - 0xb0) as usize];
+                channel.fnum = (channel.fnum & 0xff) | ((value as u16 & 0x03) << 8);
+                channel.block = (value >> 2) & 0x07;
+                let key_on = value & 0x20 != 0;
+                if key_on && !channel.key_on {
+                    channel.operators.iter_mut().for_each(Operator::key_on);
+                } else if !key_on && channel.key_on {
+                    channel.operators.iter_mut().for_each(Operator::key_off);
+                }
+                channel.key_on = key_on;
+            }
+            0xbd => {
+                self.deep_tremolo = value & 0x80 != 0;
+                self.deep_vibrato = value & 0x40 != 0;
+            }
+            0xc0..=0xc8 => {
+                let channel = &mut self.channels[(register - 0xc0) as usize];
+                channel.feedback = (value >> 1) & 0x07;
+                channel.additive = value & 0x01 != 0;
+            }
+            _ => {}
+        }
+    }
+
+    /// Next mono sample, the nine channels summed, roughly from -1 to 1.
+    pub fn sample(&mut self) -> f32 {
+        let time = self.samples as f32 / self.sample_rate;
+        self.samples += 1;
+        let lfo = Lfo {
+            tremolo_db: if self.deep_tremolo { 4.8 } else { 1.0 }
+                * (0.5 + 0.5 * (time * 3.7 * TAU).sin()),
+            vibrato: (if self.deep_vibrato { 14.0 } else { 7.0 } / 1200.0
+                * (time * 6.1 * TAU).sin())
+            .exp2(),
+        };
+        let mut channels = std::mem::take(&mut self.channels);
+        let mut output = 0.0;
+        for channel in channels.iter_mut().filter(|channel| !channel.is_silent()) {
+            let pitch = channel.pitch();
+            let [modulator, carrier] = &mut channel.operators;
+            let feedback = if channel.feedback > 0 {
+                (modulator.out[0] + modulator.out[1]) / 2.0 * (channel.feedback as f32).exp2()
+                    / 64.0
+            } else {
+                0.0
+            };
+            let first = modulator.sample(&pitch, &lfo, feedback, self);
+            modulator.out = [modulator.out[1], first];
+            output += if channel.additive {
+                first + carrier.sample(&pitch, &lfo, 0.0, self)
+            } else {
+                // A full volume modulator shifts the carrier by up to four cycles
+                carrier.sample(&pitch, &lfo, first * 4.0, self)
+            };
+        }
+        self.channels = channels;
+        output / 4.0
+    }
+}
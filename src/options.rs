@@ -0,0 +1,405 @@
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    automap::{MAX_ZOOM, MIN_ZOOM},
+    console::CVARS,
+    World,
+};
+
+/// Bumped whenever the layout of the options file changes, with a step added to `migrate`.
+pub const OPTIONS_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum OptionsError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Write(toml::ser::Error),
+}
+
+impl fmt::Display for OptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OptionsError::Io(err) => write!(f, "{err}"),
+            OptionsError::Parse(err) => write!(f, "invalid options file: {err}"),
+            OptionsError::Write(err) => write!(f, "can't write options: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for OptionsError {}
+
+impl From<std::io::Error> for OptionsError {
+    fn from(err: std::io::Error) -> Self {
+        OptionsError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for OptionsError {
+    fn from(err: toml::de::Error) -> Self {
+        OptionsError::Parse(err)
+    }
+}
+
+impl From<toml::ser::Error> for OptionsError {
+    fn from(err: toml::ser::Error) -> Self {
+        OptionsError::Write(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControlOptions {
+    #[serde(serialize_with = "short_float")]
+    pub speed: f32,
+    #[serde(serialize_with = "short_float")]
+    pub rotation_speed: f32,
+    #[serde(serialize_with = "short_float")]
+    pub mouse_sensitivity: f32,
+    #[serde(serialize_with = "short_float")]
+    pub max_pitch: f32,
+}
+
+impl Default for ControlOptions {
+    fn default() -> Self {
+        Self {
+            speed: 2.0,
+            rotation_speed: 0.05,
+            mouse_sensitivity: 0.004,
+            max_pitch: 25.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VideoOptions {
+    #[serde(serialize_with = "short_float")]
+    pub fov: f32,
+    #[serde(serialize_with = "short_float")]
+    pub fog: f32,
+    #[serde(serialize_with = "short_float")]
+    pub render_scale: f32,
+    pub view_bob: bool,
+    #[serde(serialize_with = "short_float")]
+    pub bob_strength: f32,
+    #[serde(serialize_with = "short_float")]
+    pub sway_strength: f32,
+}
+
+impl Default for VideoOptions {
+    fn default() -> Self {
+        Self {
+            fov: 60.0,
+            fog: 0.0,
+            render_scale: 1.0,
+            view_bob: true,
+            bob_strength: 1.0,
+            sway_strength: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SoundOptions {
+    #[serde(serialize_with = "short_float")]
+    pub volume: f32,
+    #[serde(serialize_with = "short_float")]
+    pub music_volume: f32,
+}
+
+impl Default for SoundOptions {
+    fn default() -> Self {
+        Self {
+            volume: 0.8,
+            music_volume: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutomapOptions {
+    #[serde(serialize_with = "short_float")]
+    pub zoom: f32,
+    pub rotate: bool,
+    pub show_enemies: bool,
+}
+
+impl Default for AutomapOptions {
+    fn default() -> Self {
+        Self {
+            zoom: 8.0,
+            rotate: false,
+            show_enemies: false,
+        }
+    }
+}
+
+/// Write an `f32` with as few digits as read it back, rather than as the `f64` TOML
+/// stores, so that 0.05 doesn't turn into 0.05000000074505806.
+fn short_float<S: serde::Serializer>(value: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+    let value: f64 = value.to_string().parse().unwrap_or(*value as f64);
+    serializer.serialize_f64(value)
+}
+
+/// Settings kept between launches, stored as TOML in the config directory. Anything
+/// missing from the file keeps its default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Options {
+    pub version: u32,
+    pub controls: ControlOptions,
+    pub video: VideoOptions,
+    pub sound: SoundOptions,
+    pub automap: AutomapOptions,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            version: OPTIONS_VERSION,
+            controls: ControlOptions::default(),
+            video: VideoOptions::default(),
+            sound: SoundOptions::default(),
+            automap: AutomapOptions::default(),
+        }
+    }
+}
+
+/// Where each setting of an unversioned file moves to: its old name, section and key.
+const SECTIONS: [(&str, &str, &str); 11] = [
+    ("speed", "controls", "speed"),
+    ("rotation_speed", "controls", "rotation_speed"),
+    ("mouse_sensitivity", "controls", "mouse_sensitivity"),
+    ("fov", "video", "fov"),
+    ("fog", "video", "fog"),
+    ("render_scale", "video", "render_scale"),
+    ("volume", "sound", "volume"),
+    ("music_volume", "sound", "music_volume"),
+    ("automap_zoom", "automap", "zoom"),
+    ("automap_rotate", "automap", "rotate"),
+    ("automap_enemies", "automap", "show_enemies"),
+];
+
+/// Bring an options file written by an older version up to `OPTIONS_VERSION`.
+fn migrate(mut table: toml::Table) -> toml::Table {
+    let version = table
+        .get("version")
+        .and_then(|version| version.as_integer())
+        .unwrap_or(0) as u32;
+    if version == 0 {
+        // Without a version the settings are a flat list named like the console cvars
+        for (name, section, key) in SECTIONS {
+            let Some(value) = table.remove(name) else {
+                continue;
+            };
+            if let toml::Value::Table(section) = table
+                .entry(section)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            {
+                section.insert(key.to_string(), value);
+            }
+        }
+    }
+    if version > OPTIONS_VERSION {
+        warn!("options file version {version} is newer than this game, reading what it can");
+    }
+    // The version stays the one the file was written with, so that `World::options` no
+    // longer matches once loaded and the file gets rewritten in the current layout
+    table.insert("version".to_string(), (version as i64).into());
+    table
+}
+
+impl Options {
+    pub fn to_toml(&self) -> Result<String, OptionsError> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    /// Parse an options file, migrating older layouts first.
+    pub fn from_toml(text: &str) -> Result<Self, OptionsError> {
+        let table: toml::Table = toml::from_str(text)?;
+        let options: Options = toml::Value::Table(migrate(table)).try_into()?;
+        Ok(options.finite_or_default())
+    }
+
+    /// Copy with `nan` and `inf` values, which TOML allows, replaced by the defaults.
+    fn finite_or_default(mut self) -> Self {
+        let defaults = Options::default();
+        let values = [
+            (&mut self.controls.speed, defaults.controls.speed),
+            (
+                &mut self.controls.rotation_speed,
+                defaults.controls.rotation_speed,
+            ),
+            (
+                &mut self.controls.mouse_sensitivity,
+                defaults.controls.mouse_sensitivity,
+            ),
+            (&mut self.controls.max_pitch, defaults.controls.max_pitch),
+            (&mut self.video.fov, defaults.video.fov),
+            (&mut self.video.fog, defaults.video.fog),
+            (&mut self.video.render_scale, defaults.video.render_scale),
+            (&mut self.video.bob_strength, defaults.video.bob_strength),
+            (&mut self.video.sway_strength, defaults.video.sway_strength),
+            (&mut self.sound.volume, defaults.sound.volume),
+            (&mut self.sound.music_volume, defaults.sound.music_volume),
+            (&mut self.automap.zoom, defaults.automap.zoom),
+        ];
+        let mut replaced = false;
+        for (value, default) in values {
+            if !value.is_finite() {
+                *value = default;
+                replaced = true;
+            }
+        }
+        if replaced {
+            // Like after migrating, so that the file gets rewritten with the defaults
+            self.version = 0;
+        }
+        self
+    }
+
+    pub fn from_world(world: &World) -> Self {
+        Self {
+            version: OPTIONS_VERSION,
+            controls: ControlOptions {
+                speed: world.speed,
+                rotation_speed: world.rotation_speed,
+                mouse_sensitivity: world.mouse_sensitivity,
+                max_pitch: world.max_pitch,
+            },
+            video: VideoOptions {
+                fov: world.fov,
+                fog: world.fog,
+                render_scale: world.render_scale,
+                view_bob: world.view_bob,
+                bob_strength: world.bob_strength,
+                sway_strength: world.sway_strength,
+            },
+            sound: SoundOptions {
+                volume: world.audio.volume(),
+                music_volume: world.audio.music_volume(),
+            },
+            automap: AutomapOptions {
+                zoom: world.automap.zoom,
+                rotate: world.automap.rotate,
+                show_enemies: world.automap.show_enemies,
+            },
+        }
+    }
+}
+
+/// File the options are kept in.
+pub fn options_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("wolfenstein-rs")
+        .join("options.toml")
+}
+
+pub fn read_options() -> Result<Options, OptionsError> {
+    Options::from_toml(&fs::read_to_string(options_path())?)
+}
+
+pub fn write_options(options: &Options) -> Result<(), OptionsError> {
+    let path = options_path();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, options.to_toml()?)?;
+    Ok(())
+}
+
+impl World {
+    /// Use `options`, clamping values to the ranges the cvars allow.
+    pub fn apply_options(&mut self, options: &Options) {
+        let values = [
+            ("speed", options.controls.speed),
+            ("rotation_speed", options.controls.rotation_speed),
+            ("mouse_sensitivity", options.controls.mouse_sensitivity),
+            ("max_pitch", options.controls.max_pitch),
+            ("fov", options.video.fov),
+            ("fog", options.video.fog),
+            ("render_scale", options.video.render_scale),
+            ("view_bob", options.video.view_bob as u8 as f32),
+            ("bob_strength", options.video.bob_strength),
+            ("sway_strength", options.video.sway_strength),
+            ("volume", options.sound.volume),
+            ("music_volume", options.sound.music_volume),
+        ];
+        for (name, value) in values {
+            if let Some(cvar) = CVARS.iter().find(|cvar| cvar.name == name) {
+                (cvar.set)(self, cvar.clamp(value));
+            }
+        }
+        self.automap.zoom = options.automap.zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        self.automap.rotate = options.automap.rotate;
+        self.automap.show_enemies = options.automap.show_enemies;
+    }
+
+    /// Read the options file at startup. A file that can't be read is moved aside so that
+    /// the next write doesn't lose it.
+    pub fn load_options(&mut self) {
+        let path = options_path();
+        match read_options() {
+            Ok(options) => {
+                info!("options loaded from {}", path.display());
+                self.apply_options(&options);
+                self.options = options;
+            }
+            Err(OptionsError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => {
+                error!("can't read {}: {err}", path.display());
+                if let Err(err) = fs::rename(&path, path.with_extension("toml.bak")) {
+                    error!("can't move {} aside: {err}", path.display());
+                }
+            }
+        }
+    }
+
+    /// Write the options back if any changed since they were last loaded or written.
+    pub fn write_changed_options(&mut self) {
+        // A demo being played has its own settings in place of the player's
+        if self.demo.is_playing() {
+            return;
+        }
+        let options = Options::from_world(self);
+        if options == self.options {
+            return;
+        }
+        if let Err(err) = write_options(&options) {
+            error!("can't write {}: {err}", options_path().display());
+        }
+        self.options = options;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::Assets;
+
+    #[test]
+    fn values_that_are_not_finite_fall_back_to_the_defaults() {
+        let text = "version = 1\n[controls]\nspeed = inf\n[video]\nfov = nan\nfog = 8.0\n";
+        let options = Options::from_toml(text).unwrap();
+        let defaults = Options::default();
+        assert_eq!(options.controls.speed, defaults.controls.speed);
+        assert_eq!(options.video.fov, defaults.video.fov);
+        assert_eq!(options.video.fog, 8.0);
+
+        let mut world = World::new(Assets::load(None));
+        world.apply_options(&options);
+        let applied = Options::from_world(&world);
+        assert_eq!(applied.video.fov, defaults.video.fov);
+        // Rewritten once with the defaults, then left alone
+        assert_ne!(applied, options);
+        assert_eq!(applied, Options::from_world(&world));
+    }
+}
@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{PI, TAU};
+
+use crate::{doors::Lock, geo::Point2, rng::Rng};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Weapon {
+    Knife,
+    Pistol,
+    MachineGun,
+    ChainGun,
+}
+
+impl Weapon {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Weapon::Knife => "KNIFE",
+            Weapon::Pistol => "PISTOL",
+            Weapon::MachineGun => "MACHINE GUN",
+            Weapon::ChainGun => "CHAIN GUN",
+        }
+    }
+
+    /// Weapon bound to a number key, counting from 1.
+    pub fn from_number(number: u32) -> Option<Self> {
+        match number {
+            1 => Some(Weapon::Knife),
+            2 => Some(Weapon::Pistol),
+            3 => Some(Weapon::MachineGun),
+            4 => Some(Weapon::ChainGun),
+            _ => None,
+        }
+    }
+
+    /// Ticks between two attacks.
+    pub fn cooldown(&self) -> u32 {
+        match self {
+            Weapon::Knife => 24,
+            Weapon::Pistol => 20,
+            Weapon::MachineGun => 10,
+            Weapon::ChainGun => 6,
+        }
+    }
+
+    /// Frame in the weapons sheet, the firing frame follows the idle one.
+    pub fn sprite(&self, firing: bool) -> usize {
+        *self as usize * 2 + firing as usize
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct Keys {
+    pub gold: bool,
+    pub silver: bool,
+}
+
+impl Keys {
+    /// Whether these keys open a door with the given lock.
+    pub fn has(&self, lock: Lock) -> bool {
+        match lock {
+            Lock::None => true,
+            Lock::Gold => self.gold,
+            Lock::Silver => self.silver,
+        }
+    }
+}
+
+/// Everything the status bar shows about the player.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerStats {
+    pub score: u32,
+    pub lives: u32,
+    pub health: u32,
+    pub ammo: u32,
+    pub weapon: Weapon,
+    /// Best weapon picked up so far, every weapon below it can be selected too.
+    pub best_weapon: Weapon,
+    pub keys: Keys,
+    /// Set once any cheat was used, until the next new game.
+    pub cheated: bool,
+}
+
+impl Default for PlayerStats {
+    fn default() -> Self {
+        Self {
+            score: 0,
+            lives: 3,
+            health: 100,
+            ammo: 8,
+            weapon: Weapon::Pistol,
+            best_weapon: Weapon::Pistol,
+            keys: Keys::default(),
+            cheated: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Look {
+    Left,
+    Center,
+    Right,
+}
+
+/// How long BJ keeps looking at whoever just hurt him, in ticks.
+const HURT_TICKS: u32 = 40;
+
+/// State of BJ's face in the status bar.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Face {
+    pub look: Look,
+    /// Ticks since the face last changed where it looks.
+    pub glance_ticks: u32,
+    pub hurt_ticks: u32,
+    pub ouch: bool,
+}
+
+impl Default for Face {
+    fn default() -> Self {
+        Self {
+            look: Look::Center,
+            glance_ticks: 0,
+            hurt_ticks: 0,
+            ouch: false,
+        }
+    }
+}
+
+impl Face {
+    /// Glance around at random intervals, like `UpdateFace` in the original.
+    pub fn update(&mut self, rng: &mut Rng) {
+        if self.hurt_ticks > 0 {
+            self.hurt_ticks -= 1;
+            return;
+        }
+        self.ouch = false;
+        self.glance_ticks += 1;
+        if self.glance_ticks <= rng.rnd() {
+            return;
+        }
+        self.glance_ticks = 0;
+        self.look = match rng.rnd() >> 6 {
+            0 => Look::Left,
+            2 => Look::Right,
+            _ => Look::Center,
+        };
+    }
+
+    /// Turn towards `source`, seen from `position` while facing `theta`.
+    pub fn hurt(&mut self, position: Point2, theta: f32, source: Option<Point2>, amount: u32) {
+        self.hurt_ticks = HURT_TICKS;
+        self.ouch = amount >= 30;
+        self.look = match source {
+            Some(source) => {
+                let to_source = source - position;
+                let mut angle = to_source.y.atan2(to_source.x) - theta;
+                angle = (angle + PI).rem_euclid(TAU) - PI;
+                if angle > 0.3 {
+                    Look::Right
+                } else if angle < -0.3 {
+                    Look::Left
+                } else {
+                    Look::Center
+                }
+            }
+            None => Look::Center,
+        };
+    }
+
+    /// Index of the frame to show in the faces sheet.
+    pub fn frame(&self, health: u32) -> usize {
+        let level = ((100 - health.min(100)) / 16).min(6) as usize;
+        if health == 0 {
+            21
+        } else if self.ouch {
+            if level < 3 {
+                22
+            } else {
+                23
+            }
+        } else {
+            level * 3
+                + match self.look {
+                    Look::Left => 0,
+                    Look::Center => 1,
+                    Look::Right => 2,
+                }
+        }
+    }
+}
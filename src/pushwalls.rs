@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{audio::Sound, map::*, World};
+
+/// Ticks a push wall takes to slide across one cell.
+const PUSHWALL_TICKS: u32 = 32;
+/// How many cells a push wall moves before it stops.
+const PUSHWALL_CELLS: u32 = 2;
+
+/// A secret wall sliding away from the player, one cell at a time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PushWall {
+    pub x: usize,
+    pub y: usize,
+    pub dx: isize,
+    pub dy: isize,
+    pub tile: u8,
+    /// Cells moved so far.
+    pub moved: u32,
+    pub ticks: u32,
+}
+
+impl World {
+    /// Start pushing the secret wall at `x`, `y` in the direction the player faces.
+    /// Returns false if it isn't a push wall or has no room to move.
+    pub fn push_wall(&mut self, x: usize, y: usize) -> bool {
+        if self.map.object(x, y) != PUSHWALL {
+            return false;
+        }
+        let (dx, dy) = if self.theta.cos().abs() > self.theta.sin().abs() {
+            (self.theta.cos().signum() as isize, 0)
+        } else {
+            (0, self.theta.sin().signum() as isize)
+        };
+        if self.pushwall_target(x, y, dx, dy).is_none() {
+            return false;
+        }
+        self.map.set_object(x, y, 0);
+        self.tally.secrets += 1;
+        self.play_sound(Sound::PushWall, Some(cell_center(x, y)));
+        self.pushwalls.push(PushWall {
+            x,
+            y,
+            dx,
+            dy,
+            tile: self.map.tile(x, y),
+            moved: 0,
+            ticks: 0,
+        });
+        true
+    }
+
+    /// Next cell in the given direction, if it's empty.
+    fn pushwall_target(&self, x: usize, y: usize, dx: isize, dy: isize) -> Option<(usize, usize)> {
+        let x = x.checked_add_signed(dx)?;
+        let y = y.checked_add_signed(dy)?;
+        (x < self.map.width && y < self.map.height && self.map.tile(x, y) == 0).then_some((x, y))
+    }
+
+    pub fn update_pushwalls(&mut self) {
+        let player_cell = self.map.cell_at(self.x, self.y);
+        for i in 0..self.pushwalls.len() {
+            let wall = self.pushwalls[i].clone();
+            if wall.ticks + 1 < PUSHWALL_TICKS {
+                self.pushwalls[i].ticks += 1;
+                continue;
+            }
+            match self.pushwall_target(wall.x, wall.y, wall.dx, wall.dy) {
+                // Wait for the player to get out of the way
+                Some(target) if Some(target) == player_cell => {}
+                Some((x, y)) => {
+                    self.map.set_tile(wall.x, wall.y, 0);
+                    self.map.set_tile(x, y, wall.tile);
+                    // The wall takes its height and the floor it stands on along, and
+                    // leaves the floor it slides onto behind
+                    let (from, to) = (wall.x + wall.y * self.map.width, x + y * self.map.width);
+                    for plane in [&mut self.map.heights, &mut self.map.floors] {
+                        if to < plane.len() {
+                            plane.swap(from, to);
+                        }
+                    }
+                    let wall = &mut self.pushwalls[i];
+                    wall.x = x;
+                    wall.y = y;
+                    wall.moved += 1;
+                    wall.ticks = 0;
+                }
+                None => self.pushwalls[i].moved = PUSHWALL_CELLS,
+            }
+        }
+        self.pushwalls.retain(|wall| wall.moved < PUSHWALL_CELLS);
+    }
+}
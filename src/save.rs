@@ -0,0 +1,290 @@
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actors::Actor,
+    cheats::Cheats,
+    difficulty::Difficulty,
+    doors::Door,
+    hud::Message,
+    intermission::Tally,
+    levels::LEVELS,
+    map::Map,
+    objects::Object,
+    player::{Face, PlayerStats},
+    pushwalls::PushWall,
+    rng::Rng,
+    World, EYE_HEIGHT,
+};
+
+/// Bumped whenever `SaveGame` changes in a way older saves can't be read with.
+pub const SAVE_VERSION: u32 = 5;
+pub const SAVE_SLOTS: usize = 10;
+/// Slot used by the quick save and quick load keys.
+pub const QUICK_SAVE_SLOT: usize = 0;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Format(serde_json::Error),
+    /// The save was written by a different version of the game.
+    Version(u32),
+    /// Saving isn't possible right now, e.g. during the intermission.
+    NotNow,
+    /// Part of the save doesn't fit the level it was saved on.
+    Mismatch(&'static str),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "{err}"),
+            SaveError::Format(err) => write!(f, "invalid save file: {err}"),
+            SaveError::Version(version) => write!(
+                f,
+                "save file version {version} is not supported (expected {SAVE_VERSION})"
+            ),
+            SaveError::NotNow => write!(f, "can't save right now"),
+            SaveError::Mismatch(what) => write!(f, "{what} in save doesn't match its level"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<std::io::Error> for SaveError {
+    fn from(err: std::io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(err: serde_json::Error) -> Self {
+        SaveError::Format(err)
+    }
+}
+
+/// Everything needed to resume a game, stored as JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub version: u32,
+    /// Shown next to the slot, e.g. "E1M1 Entry Hall".
+    pub description: String,
+    pub level_index: usize,
+    /// Whether `map` is a generated or loaded map rather than the level at `level_index`.
+    #[serde(default)]
+    pub custom_map: bool,
+    pub difficulty: Difficulty,
+    pub x: f32,
+    pub y: f32,
+    pub theta: f32,
+    pub stats: PlayerStats,
+    pub face: Face,
+    pub ticks: u64,
+    /// The level as it is now, with moved push walls and used secrets.
+    pub map: Map,
+    pub doors: Vec<Door>,
+    /// Objects still lying around.
+    pub objects: Vec<Object>,
+    pub actors: Vec<Actor>,
+    /// Cells revealed on the automap.
+    pub seen: Vec<bool>,
+    pub pushwalls: Vec<PushWall>,
+    pub tally: Tally,
+    pub attack_ticks: u32,
+    pub rng: Rng,
+    /// Cheats that were on, `stats.cheated` telling whether any were ever used.
+    pub cheats: Cheats,
+}
+
+impl SaveGame {
+    pub fn to_json(&self) -> Result<String, SaveError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Parse a save, checking its version before anything else.
+    pub fn from_json(json: &str) -> Result<Self, SaveError> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        let version = value
+            .get("version")
+            .and_then(|version| version.as_u64())
+            .unwrap_or(0) as u32;
+        if version != SAVE_VERSION {
+            return Err(SaveError::Version(version));
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Make sure the level index exists and every per-cell plane covers the whole map.
+    pub fn check(&self) -> Result<(), SaveError> {
+        if self.level_index >= LEVELS.len() {
+            return Err(SaveError::Mismatch("level"));
+        }
+        let cells = self.map.width * self.map.height;
+        let planes = [
+            ("walls", self.map.walls.len()),
+            ("objects", self.map.objects.len()),
+            ("seen cells", self.seen.len()),
+        ];
+        if let Some((what, _)) = planes.into_iter().find(|&(_, len)| len != cells) {
+            return Err(SaveError::Mismatch(what));
+        }
+        let map = &self.map;
+        let in_map = |x: usize, y: usize| x < map.width && y < map.height;
+        let on_map = |x: f32, y: f32| x.is_finite() && y.is_finite() && map.cell_at(x, y).is_some();
+        if !on_map(self.x, self.y) {
+            return Err(SaveError::Mismatch("player position"));
+        }
+        if !self.doors.iter().all(|door| in_map(door.x, door.y)) {
+            return Err(SaveError::Mismatch("door"));
+        }
+        if !self
+            .pushwalls
+            .iter()
+            .all(|wall| in_map(wall.x, wall.y) && wall.dx.abs() <= 1 && wall.dy.abs() <= 1)
+        {
+            return Err(SaveError::Mismatch("push wall"));
+        }
+        if !self.actors.iter().all(|actor| on_map(actor.x, actor.y)) {
+            return Err(SaveError::Mismatch("guard"));
+        }
+        // Heights and floors may be left out for a flat map
+        let optional = [
+            ("heights", self.map.heights.len()),
+            ("floors", self.map.floors.len()),
+        ];
+        if let Some((what, _)) = optional
+            .into_iter()
+            .find(|&(_, len)| len != 0 && len != cells)
+        {
+            return Err(SaveError::Mismatch(what));
+        }
+        Ok(())
+    }
+}
+
+/// Directory save slots are written to.
+pub fn save_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("wolfenstein-rs")
+        .join("saves")
+}
+
+pub fn slot_path(slot: usize) -> PathBuf {
+    save_dir().join(format!("slot{slot}.json"))
+}
+
+pub fn write_slot(slot: usize, save: &SaveGame) -> Result<(), SaveError> {
+    fs::create_dir_all(save_dir())?;
+    fs::write(slot_path(slot), save.to_json()?)?;
+    Ok(())
+}
+
+pub fn read_slot(slot: usize) -> Result<SaveGame, SaveError> {
+    SaveGame::from_json(&fs::read_to_string(slot_path(slot))?)
+}
+
+/// Description of each slot, `None` for empty or unreadable ones.
+pub fn list_slots() -> Vec<Option<String>> {
+    (0..SAVE_SLOTS)
+        .map(|slot| read_slot(slot).ok().map(|save| save.description))
+        .collect()
+}
+
+impl World {
+    /// Saving isn't possible between leaving a level and starting the next one.
+    pub fn can_save(&self) -> bool {
+        self.intermission.is_none() && self.exit.is_none()
+    }
+
+    pub fn save_game(&self) -> Result<SaveGame, SaveError> {
+        if !self.can_save() {
+            return Err(SaveError::NotNow);
+        }
+        let mut description = if self.custom_map {
+            self.level_name()
+        } else {
+            format!("{} {}", self.level_name(), self.map.name)
+        };
+        if self.stats.cheated {
+            description.push_str(" (cheats)");
+        }
+        Ok(SaveGame {
+            version: SAVE_VERSION,
+            description,
+            level_index: self.level_index,
+            custom_map: self.custom_map,
+            difficulty: self.difficulty,
+            x: self.x,
+            y: self.y,
+            theta: self.theta,
+            stats: self.stats.clone(),
+            face: self.face.clone(),
+            ticks: self.ticks,
+            map: self.map.clone(),
+            doors: self.doors.clone(),
+            objects: self.objects.clone(),
+            actors: self.actors.clone(),
+            seen: self.seen.clone(),
+            pushwalls: self.pushwalls.clone(),
+            tally: self.tally.clone(),
+            attack_ticks: self.attack_ticks,
+            rng: self.rng.clone(),
+            cheats: self.cheats.clone(),
+        })
+    }
+
+    /// Resume a save, leaving the game as it was if the save doesn't fit together.
+    pub fn load_game(&mut self, save: SaveGame) -> Result<(), SaveError> {
+        save.check()?;
+        self.difficulty = save.difficulty;
+        self.load_level(save.level_index);
+        self.custom_map = save.custom_map;
+        self.x = save.x;
+        self.y = save.y;
+        self.theta = save.theta;
+        self.stats = save.stats;
+        self.face = save.face;
+        self.ticks = save.ticks;
+        self.map = save.map;
+        self.doors = save.doors;
+        self.objects = save.objects;
+        self.actors = save.actors;
+        self.seen = save.seen;
+        self.pushwalls = save.pushwalls;
+        self.tally = save.tally;
+        self.attack_ticks = save.attack_ticks;
+        self.rng = save.rng;
+        self.cheats = save.cheats;
+        // The view follows the saved position rather than the level start
+        self.view_floor = self.floor_under(self.x, self.y);
+        self.eye_height = EYE_HEIGHT;
+        Ok(())
+    }
+
+    /// Save to a slot, reporting the outcome as a message.
+    pub fn save_to_slot(&mut self, slot: usize) {
+        let result = self.save_game().and_then(|save| write_slot(slot, &save));
+        self.message = Some(Message::new(match result {
+            Ok(()) => format!("Game saved to slot {slot}"),
+            Err(err) => format!("Save failed: {err}"),
+        }));
+    }
+
+    /// Load from a slot, reporting the outcome as a message. Returns whether it loaded.
+    pub fn load_from_slot(&mut self, slot: usize) -> bool {
+        if self.refuse_while_recording("load a game") {
+            return false;
+        }
+        let result = read_slot(slot).and_then(|save| self.load_game(save));
+        self.message = Some(Message::new(match &result {
+            Ok(()) => format!("Loaded slot {slot}"),
+            Err(err) => format!("Load failed: {err}"),
+        }));
+        result.is_ok()
+    }
+}
@@ -0,0 +1,146 @@
+use std::f32::consts::TAU;
+
+use glam::Vec2;
+
+use crate::{
+    actors::*, audio::Sound, geo::*, graphics::*, objects::*, player::*, Controls, World,
+    VIEW_HEIGHT, WIDTH,
+};
+
+/// How far the knife reaches.
+const KNIFE_RANGE: f32 = 30.0;
+/// Half the width of a guard, for deciding whether a shot hits.
+const ACTOR_RADIUS: f32 = 10.0;
+/// How long the firing frame stays up, in ticks.
+const FIRE_FRAME_TICKS: u32 = 6;
+/// Size of the weapon drawn at the bottom of the view.
+const WEAPON_SIZE: f32 = 192.0;
+/// How far the weapon sways sideways and dips while walking at full speed, in pixels.
+const SWAY_WIDTH: f32 = 12.0;
+const SWAY_DIP: f32 = 6.0;
+
+impl World {
+    pub fn update_weapon(&mut self, controls: &Controls) {
+        if let Some(weapon) = controls.weapon {
+            if weapon <= self.stats.best_weapon && (weapon == Weapon::Knife || self.stats.ammo > 0)
+            {
+                self.stats.weapon = weapon;
+            }
+        }
+        self.attack_ticks = self.attack_ticks.saturating_sub(1);
+        if !controls.fire || self.attack_ticks > 0 {
+            return;
+        }
+        if self.stats.weapon != Weapon::Knife && self.stats.ammo == 0 {
+            self.stats.weapon = Weapon::Knife;
+            return;
+        }
+        self.attack_ticks = self.stats.weapon.cooldown();
+        self.fire();
+    }
+
+    /// Attack with the current weapon, hitting the closest visible guard in the line of fire.
+    fn fire(&mut self) {
+        let knife = self.stats.weapon == Weapon::Knife;
+        let sound = match self.stats.weapon {
+            Weapon::Knife => Sound::Knife,
+            Weapon::Pistol => Sound::Pistol,
+            Weapon::MachineGun => Sound::MachineGun,
+            Weapon::ChainGun => Sound::ChainGun,
+        };
+        self.play_sound(sound, None);
+        if !knife {
+            self.stats.ammo -= 1;
+            self.alert_actors();
+        }
+        let player = Vec2::new(self.x, self.y);
+        let target = self
+            .actors
+            .iter()
+            .enumerate()
+            .filter(|(_, actor)| actor.is_alive())
+            .filter_map(|(i, actor)| {
+                let to_actor = actor.position() - player;
+                let distance = to_actor.length();
+                let angle = to_actor.y.atan2(to_actor.x) - self.theta;
+                let ahead = angle.cos() * distance;
+                let across = (angle.sin() * distance).abs();
+                (ahead > 0.0
+                    && across < ACTOR_RADIUS
+                    && (!knife || distance < KNIFE_RANGE)
+                    && self.line_of_sight(player, actor.position()))
+                .then_some((i, distance))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        let Some((index, distance)) = target else {
+            return;
+        };
+        // Damage falls off with distance, as in the original `GunAttack`
+        let tiles = (distance / crate::map::TILE_WIDTH) as u32;
+        let damage = if knife {
+            self.rng.rnd() / 16
+        } else if tiles < 2 {
+            self.rng.rnd() / 4
+        } else if tiles < 4 {
+            self.rng.rnd() / 6
+        } else {
+            if self.rng.rnd() / 12 < tiles {
+                return;
+            }
+            self.rng.rnd() / 6
+        };
+        self.hurt_actor(index, damage as i32);
+    }
+
+    fn hurt_actor(&mut self, index: usize, damage: i32) {
+        let actor = &mut self.actors[index];
+        actor.health -= damage;
+        let (x, y) = (actor.x, actor.y);
+        if actor.health > 0 {
+            actor.state = ActorState::Pain(PAIN_TICKS);
+            self.play_sound(Sound::GuardPain, Some(Point2::new(x, y)));
+            return;
+        }
+        actor.state = ActorState::Dead;
+        self.play_sound(Sound::GuardDeath, Some(Point2::new(x, y)));
+        self.stats.score += GUARD_POINTS;
+        self.tally.kills += 1;
+        self.objects.push(Object {
+            x,
+            y,
+            kind: ObjectKind::AmmoClip,
+        });
+    }
+
+    /// Offset of the weapon from its resting place while walking: side to side once per
+    /// bob cycle, dipping at every step.
+    pub fn weapon_sway(&self) -> (f32, f32) {
+        if !self.view_bob {
+            return (0.0, 0.0);
+        }
+        let amount = self.sway_strength * self.bob_amount;
+        let angle = self.bob_phase * TAU;
+        (
+            angle.sin() * SWAY_WIDTH * amount,
+            angle.sin().abs() * SWAY_DIP * amount,
+        )
+    }
+
+    /// Draw the current weapon at the bottom of the view.
+    pub fn draw_weapon(&self, frame: &mut [u8]) {
+        let weapon = self.stats.weapon;
+        let firing = self.attack_ticks + FIRE_FRAME_TICKS > weapon.cooldown();
+        let sheet = &self.assets.weapons;
+        let (sway_x, sway_y) = self.weapon_sway();
+        TextureRect::new(
+            (WIDTH as f32 - WEAPON_SIZE) / 2.0 + sway_x,
+            VIEW_HEIGHT as f32 - WEAPON_SIZE + sway_y,
+            WEAPON_SIZE,
+            WEAPON_SIZE,
+            sheet.frame(weapon.sprite(firing)),
+            sheet.frame_width,
+            sheet.frame_height,
+        )
+        .draw(frame);
+    }
+}
@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use wolfenstein_rs::{assets::Assets, demo::*, Controls, World};
+
+/// 600 ticks on E1M1 at medium skill: walking, turning with the mouse, looking up, opening
+/// doors and killing a guard.
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/e1m1.json");
+
+fn assert_end_state(world: &World) {
+    assert!((world.x - 299.12787).abs() < 1e-3, "x is {}", world.x);
+    assert!((world.y - 57.58306).abs() < 1e-3, "y is {}", world.y);
+    assert!(
+        (world.theta - 3.6492012).abs() < 1e-4,
+        "theta is {}",
+        world.theta
+    );
+    assert_eq!(world.stats.score, 100);
+    assert_eq!(world.stats.health, 100);
+    assert_eq!(world.stats.ammo, 4);
+    assert_eq!(world.tally.kills, 1);
+    assert_eq!(world.tally.ticks, 600);
+}
+
+#[test]
+fn replays_to_the_recorded_end() {
+    let demo = Demo::load(Path::new(FIXTURE)).unwrap();
+    let mut world = World::new(Assets::load(None));
+    // The player's own settings must not change how the demo plays
+    world.speed *= 2.0;
+    world.mouse_sensitivity *= 3.0;
+    demo.run(&mut world);
+    assert_end_state(&world);
+}
+
+#[test]
+fn playback_puts_the_players_settings_back() {
+    let mut world = World::new(Assets::load(None));
+    world.speed *= 2.0;
+    let speed = world.speed;
+    world.play_demo(Path::new(FIXTURE));
+    while world.demo.is_playing() {
+        if matches!(world.demo, DemoState::Playing(_, 600, _)) {
+            assert_end_state(&world);
+        }
+        world.tick(&Controls::default());
+    }
+    assert_eq!(world.speed, speed);
+}
+
+#[test]
+fn recording_refuses_cheats_and_setting_changes() {
+    let mut world = World::new(Assets::load(None));
+    world.start_recording(1);
+    world.execute("god");
+    world.toggle_cheat(wolfenstein_rs::cheats::Cheat::Noclip);
+    world.give_all();
+    world.execute("speed 1");
+    let speed = world.speed;
+    world.tick(&Controls::default());
+    assert!(!world.cheats.god && !world.cheats.noclip);
+    assert!(!world.stats.cheated);
+    assert_eq!(world.speed, speed);
+    assert_ne!(world.speed, 1.0);
+}
+
+#[test]
+fn custom_maps_are_not_recorded() {
+    let mut world = World::new(Assets::load(None));
+    world.random_level(&wolfenstein_rs::generator::GeneratorOptions::default());
+    let map = world.map.clone();
+    world.start_recording(1);
+    assert!(!world.demo.is_recording());
+    assert_eq!(world.map, map);
+}
@@ -0,0 +1,49 @@
+use wolfenstein_rs::{audio::SAMPLE_RATE, music::*};
+
+/// One A4 on the first channel for a tenth of a second, then released.
+const NOTE: &[u8] = include_bytes!("fixtures/note.imf");
+
+/// Left channel of the first `frames` frames of `imf`, at full volume.
+fn render(imf: Imf, frames: usize) -> Vec<f32> {
+    let mut player = MusicPlayer::new("note", imf);
+    let mut out = vec![0.0; frames * 2];
+    player.render(&mut out, 1.0);
+    out.chunks_exact(2).map(|frame| frame[0]).collect()
+}
+
+#[test]
+fn parses_the_fixture() {
+    let imf = Imf::parse(NOTE).unwrap();
+    assert_eq!(imf.commands.len(), 12);
+    assert_eq!(
+        imf.commands[10],
+        ImfCommand {
+            register: 0xb0,
+            value: 0x32,
+            delay: 70,
+        }
+    );
+}
+
+#[test]
+fn renders_the_recorded_samples() {
+    let samples = render(Imf::parse(NOTE).unwrap(), SAMPLE_RATE as usize / 10);
+    let peak = samples
+        .iter()
+        .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+    assert!(peak > 0.01, "note is silent, peak {peak}");
+    // Recorded from a known good render, allowing for `sin` differing a little between platforms
+    let expected = [
+        (1000, [-0.11499247, 0.0782641, 0.08891161, -0.23009902]),
+        (3000, [0.17150253, 0.16218698, -0.16201185, 0.19606228]),
+    ];
+    for (start, slice) in expected {
+        for (offset, (sample, expected)) in samples[start..].iter().zip(slice).enumerate() {
+            assert!(
+                (sample - expected).abs() < 1e-3,
+                "sample {} is {sample}, expected {expected}",
+                start + offset
+            );
+        }
+    }
+}
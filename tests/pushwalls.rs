@@ -0,0 +1,34 @@
+use wolfenstein_rs::{assets::Assets, map::*, World};
+
+#[test]
+fn push_walls_take_their_height_and_floor_along() {
+    let mut map = Map::new(6, 3);
+    for x in 0..6 {
+        map.set_tile(x, 0, 1);
+        map.set_tile(x, 2, 1);
+    }
+    map.set_tile(0, 1, 1);
+    map.set_tile(5, 1, 1);
+    map.set_object(1, 1, PLAYER_START_EAST);
+    map.set_tile(2, 1, 1);
+    map.set_object(2, 1, PUSHWALL);
+    map.heights[2 + 6] = 2 * HEIGHT_STEPS;
+    map.floors[2 + 6] = MAX_STEP;
+    map.floors[3 + 6] = 1;
+    let mut world = World::new(Assets::load(None));
+    world.start_map(map);
+
+    assert!(world.push_wall(2, 1));
+    while world.map.tile(3, 1) == 0 {
+        world.update_pushwalls();
+    }
+    assert_eq!(world.map.tile(2, 1), 0);
+    assert_eq!(
+        (world.map.wall_steps(3, 1), world.map.floor_steps(3, 1)),
+        (16, 4)
+    );
+    assert_eq!(
+        (world.map.wall_steps(2, 1), world.map.floor_steps(2, 1)),
+        (0, 1)
+    );
+}
@@ -0,0 +1,49 @@
+use wolfenstein_rs::{assets::Assets, map::*, save::*, World};
+
+fn world() -> World {
+    World::new(Assets::load(None))
+}
+
+#[test]
+fn saves_round_trip() {
+    let mut world = world();
+    let save = world.save_game().unwrap();
+    let save = SaveGame::from_json(&save.to_json().unwrap()).unwrap();
+    assert!(world.load_game(save).is_ok());
+}
+
+#[test]
+fn edited_saves_are_refused() {
+    let mut world = world();
+    let save = world.save_game().unwrap();
+    let edits: [fn(&mut SaveGame); 5] = [
+        |save| {
+            save.seen.pop();
+        },
+        |save| save.doors[0].x = save.map.width,
+        |save| save.actors[0].y = f32::NAN,
+        |save| save.x = -1.0,
+        |save| save.level_index = 99,
+    ];
+    for edit in edits {
+        let mut save = save.clone();
+        edit(&mut save);
+        assert!(matches!(world.load_game(save), Err(SaveError::Mismatch(_))));
+    }
+}
+
+#[test]
+fn loading_on_a_raised_floor_looks_from_it() {
+    let mut world = world();
+    let (x, y) = world.map.cell_at(world.x, world.y).unwrap();
+    let mut map = world.map.clone();
+    map.floors[x + y * map.width] = HEIGHT_STEPS / 2;
+    world.set_map(map);
+    world.view_floor = world.floor_under(world.x, world.y);
+    let save = world.save_game().unwrap();
+
+    let mut loaded = self::world();
+    loaded.load_game(save).unwrap();
+    assert_eq!(loaded.view_floor, 0.5);
+    assert_eq!(loaded.eye_z(), world.eye_z());
+}